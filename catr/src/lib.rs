@@ -1,12 +1,65 @@
-use clap::{Arg, Command};
-use command_utils::{open, MyResult};
-use std::io::BufRead;
+use clap::{value_parser, Arg, Command};
+use command_utils::{open, open_decompressed, MyResult};
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+
+#[derive(Debug, Eq, PartialEq)]
+enum NumberFormat {
+    /// Left justified, without leading zeros.
+    Left,
+    /// Right justified, without leading zeros.
+    Right,
+    /// Right justified, with leading zeros.
+    RightZero,
+}
+
+/// Formatting state carried over from one input file to the next.
+#[derive(Debug)]
+struct FormatState {
+    /// The next line number to print.
+    line_number: usize,
+    /// Whether the output is at the start of a line, i.e. the previous input ended with a
+    /// newline.
+    line_start: bool,
+    /// Whether the last line written was blank, used by --squeeze-blank.
+    prev_blank: bool,
+}
+
+impl FormatState {
+    fn new(line_number: usize) -> FormatState {
+        FormatState {
+            line_number,
+            line_start: true,
+            prev_blank: false,
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct Config {
     files: Vec<String>,
     number_lines: bool,
     number_nonblank_lines: bool,
+    show_ends: bool,
+    show_tabs: bool,
+    show_nonprinting: bool,
+    squeeze_blank: bool,
+    number_per_file: bool,
+    number_width: usize,
+    number_separator: String,
+    starting_line_number: usize,
+    number_format: NumberFormat,
+    header: bool,
+    separator: Option<String>,
+    decompress: bool,
+}
+
+/// An opened input file. Plain files and standard input are kept unwrapped when no formatting
+/// or decompression is requested, so that they can be copied to standard output by the kernel.
+enum Input {
+    Stdin,
+    File(File),
+    Reader(Box<dyn BufRead>),
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -36,8 +89,107 @@ pub fn get_args() -> MyResult<Config> {
                 .help("Number the non-blank output lines, starting at 1.")
                 .num_args(0),
         )
+        .arg(
+            Arg::new("show_all")
+                .short('A')
+                .long("show-all")
+                .help("Equivalent to -vET.")
+                .num_args(0),
+        )
+        .arg(
+            Arg::new("show_ends")
+                .short('E')
+                .long("show-ends")
+                .help("Display $ at the end of each line.")
+                .num_args(0),
+        )
+        .arg(
+            Arg::new("show_tabs")
+                .short('T')
+                .long("show-tabs")
+                .help("Display TAB characters as ^I.")
+                .num_args(0),
+        )
+        .arg(
+            Arg::new("show_nonprinting")
+                .short('v')
+                .long("show-nonprinting")
+                .help("Use ^ and M- notation, except for LFD and TAB.")
+                .num_args(0),
+        )
+        .arg(
+            Arg::new("squeeze_blank")
+                .short('s')
+                .long("squeeze-blank")
+                .help("Suppress repeated empty output lines.")
+                .num_args(0),
+        )
+        .arg(
+            Arg::new("number_per_file")
+                .long("number-per-file")
+                .help("Restart line numbering for each input file.")
+                .num_args(0),
+        )
+        .arg(
+            Arg::new("number_width")
+                .long("number-width")
+                .value_name("WIDTH")
+                .help("Use WIDTH columns for line numbers.")
+                .num_args(1)
+                .default_value("6")
+                .value_parser(value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("number_separator")
+                .long("number-separator")
+                .value_name("STRING")
+                .help("Add STRING after the line number.")
+                .num_args(1)
+                .default_value("\t")
+                .allow_hyphen_values(true),
+        )
+        .arg(
+            Arg::new("starting_line_number")
+                .long("starting-line-number")
+                .value_name("NUMBER")
+                .help("First line number.")
+                .num_args(1)
+                .default_value("1")
+                .value_parser(value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("number_format")
+                .long("number-format")
+                .value_name("FORMAT")
+                .help("Insert line numbers according to FORMAT: ln (left justified), rn (right justified) or rz (right justified, leading zeros).")
+                .num_args(1)
+                .default_value("rn")
+                .value_parser(["ln", "rn", "rz"]),
+        )
+        .arg(
+            Arg::new("header")
+                .long("header")
+                .help("Print a ==> FILE <== banner before each input file.")
+                .num_args(0),
+        )
+        .arg(
+            Arg::new("separator")
+                .long("separator")
+                .value_name("STRING")
+                .help("Print STRING on a line of its own between input files.")
+                .num_args(1)
+                .allow_hyphen_values(true)
+                .conflicts_with("header"),
+        )
+        .arg(
+            Arg::new("decompress")
+                .long("decompress")
+                .help("Decompress .gz, .xz, .zst and .bz2 input files.")
+                .num_args(0),
+        )
         .get_matches();
 
+    let show_all = matches.get_flag("show_all");
     Ok(Config {
         files: matches
             .get_many::<String>("input_files")
@@ -46,31 +198,230 @@ pub fn get_args() -> MyResult<Config> {
             .collect(),
         number_lines: matches.get_flag("number_lines"),
         number_nonblank_lines: matches.get_flag("number_nonblank_lines"),
+        show_ends: show_all || matches.get_flag("show_ends"),
+        show_tabs: show_all || matches.get_flag("show_tabs"),
+        show_nonprinting: show_all || matches.get_flag("show_nonprinting"),
+        squeeze_blank: matches.get_flag("squeeze_blank"),
+        number_per_file: matches.get_flag("number_per_file"),
+        number_width: *matches.get_one::<usize>("number_width").unwrap(),
+        number_separator: matches
+            .get_one::<String>("number_separator")
+            .unwrap()
+            .to_owned(),
+        starting_line_number: *matches.get_one::<usize>("starting_line_number").unwrap(),
+        number_format: match matches.get_one::<String>("number_format").unwrap().as_str() {
+            "ln" => NumberFormat::Left,
+            "rn" => NumberFormat::Right,
+            "rz" => NumberFormat::RightZero,
+            _ => unreachable!("Invalid number format"),
+        },
+        header: matches.get_flag("header"),
+        separator: matches.get_one::<String>("separator").map(|s| s.to_owned()),
+        decompress: matches.get_flag("decompress"),
     })
 }
+
 pub fn run(config: Config) -> MyResult<()> {
-    for f in config.files {
-        match open(&f) {
-            Err(e) => eprintln!("{f}: {e}"),
-            Ok(b) => {
-                let mut counter = 1;
-                for (number, line) in b.lines().enumerate() {
-                    let line = line?;
-                    if config.number_lines {
-                        println!("{:>6}\t{line}", number + 1)
-                    } else if config.number_nonblank_lines {
-                        if !line.is_empty() {
-                            println!("{:>6}\t{line}", counter);
-                            counter += 1;
-                        } else {
-                            println!("{line}");
-                        }
-                    } else {
-                        println!("{line}");
-                    }
+    let formatted = config.number_lines
+        || config.number_nonblank_lines
+        || config.show_ends
+        || config.show_tabs
+        || config.show_nonprinting
+        || config.squeeze_blank;
+    let mut stdout = io::stdout().lock();
+    let mut state = FormatState::new(config.starting_line_number);
+    let mut first = true;
+
+    for f in &config.files {
+        let input = match open_input(f, &config, formatted) {
+            Err(e) => {
+                eprintln!("{e}");
+                continue;
+            }
+            Ok(input) => input,
+        };
+
+        let banner = match &config.separator {
+            Some(separator) if !first => Some(format!("{separator}\n")),
+            Some(_) => None,
+            None if config.header => {
+                Some(format!("{}==> {f} <==\n", if first { "" } else { "\n" }))
+            }
+            None => None,
+        };
+        if let Some(banner) = banner {
+            // The separator goes on a line of its own, and the header starts with a newline.
+            if config.separator.is_some() && !state.line_start {
+                stdout.write_all(b"\n")?;
+            }
+            stdout.write_all(banner.as_bytes())?;
+            state.line_start = true;
+        }
+        first = false;
+
+        if config.number_per_file {
+            state = FormatState::new(config.starting_line_number);
+        }
+
+        // Without any formatting, the input is copied unchanged. io::copy uses
+        // copy_file_range, splice or sendfile on Linux when both ends allow it, and
+        // falls back to a buffered copy otherwise.
+        let res: MyResult<()> = match input {
+            Input::Reader(b) if formatted => {
+                let mut out = BufWriter::new(&mut stdout);
+                format_lines(b, &config, &mut state, &mut out).and_then(|_| Ok(out.flush()?))
+            }
+            // Watching for the newline at the end keeps the kernel out of the copy, so it's
+            // only done when a separator follows.
+            input if config.separator.is_some() => {
+                let mut out = LineEnd {
+                    inner: &mut stdout,
+                    line_start: state.line_start,
+                };
+                let res = copy_input(input, &mut out);
+                state.line_start = out.line_start;
+                res
+            }
+            input => copy_input(input, &mut stdout),
+        };
+        if let Err(e) = res {
+            eprintln!("{f}: {e}");
+        }
+    }
+    Ok(())
+}
+
+/// Copies `input` to `out` unchanged.
+fn copy_input(input: Input, out: &mut impl Write) -> MyResult<()> {
+    match input {
+        Input::Stdin => io::copy(&mut io::stdin().lock(), out),
+        Input::File(mut file) => io::copy(&mut file, out),
+        Input::Reader(mut b) => io::copy(&mut b, out),
+    }?;
+    Ok(())
+}
+
+/// A writer that remembers whether the last byte written to `inner` was a newline.
+struct LineEnd<W> {
+    inner: W,
+    line_start: bool,
+}
+
+impl<W: Write> Write for LineEnd<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        if n > 0 {
+            self.line_start = buf[n - 1] == b'\n';
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Opens `filename`. Like [`open`], errors start with the file name.
+fn open_input(filename: &str, config: &Config, formatted: bool) -> MyResult<Input> {
+    Ok(if config.decompress {
+        Input::Reader(open_decompressed(filename)?)
+    } else if formatted {
+        Input::Reader(open(filename)?)
+    } else if filename == "-" {
+        Input::Stdin
+    } else {
+        Input::File(File::open(filename).map_err(|e| format!("{filename}: {e}"))?)
+    })
+}
+
+/// Writes the lines read from `reader` to `out`, applying the numbering and display options.
+/// Lines are handled as raw bytes, so that carriage returns and invalid UTF-8 are visualized
+/// (or passed through unchanged) rather than stripped or rejected. Like GNU cat, numbering
+/// continues across files, and a file that doesn't end with a newline is continued by the
+/// first line of the next one.
+fn format_lines(
+    mut reader: impl BufRead,
+    config: &Config,
+    state: &mut FormatState,
+    out: &mut impl Write,
+) -> MyResult<()> {
+    let mut line = Vec::new();
+
+    while reader.read_until(b'\n', &mut line)? > 0 {
+        let line_start = state.line_start;
+        state.line_start = line.ends_with(b"\n");
+
+        let blank = line_start && line == b"\n";
+        if config.squeeze_blank && blank && state.prev_blank {
+            line.clear();
+            continue;
+        }
+        state.prev_blank = blank;
+
+        if line_start && (config.number_lines || (config.number_nonblank_lines && !blank)) {
+            let counter = state.line_number;
+            let width = config.number_width;
+            match config.number_format {
+                NumberFormat::Left => write!(out, "{counter:<width$}")?,
+                NumberFormat::Right => write!(out, "{counter:>width$}")?,
+                NumberFormat::RightZero => write!(out, "{counter:0>width$}")?,
+            }
+            out.write_all(config.number_separator.as_bytes())?;
+            state.line_number += 1;
+        }
+
+        match line.strip_suffix(b"\n") {
+            // Like GNU cat, show the carriage return of a CRLF ending along with the `$`.
+            Some(content) if config.show_ends => match content.strip_suffix(b"\r") {
+                Some(content) => {
+                    write_visible(content, config, out)?;
+                    out.write_all(b"^M$\n")?;
+                }
+                None => {
+                    write_visible(content, config, out)?;
+                    out.write_all(b"$\n")?;
+                }
+            },
+            Some(content) => {
+                write_visible(content, config, out)?;
+                out.write_all(b"\n")?;
+            }
+            None => write_visible(&line, config, out)?,
+        }
+        line.clear();
+    }
+
+    Ok(())
+}
+
+/// Writes `content` to `out`, replacing tabs with `^I` and non-printing bytes with the
+/// `^` and `M-` notation, when requested.
+fn write_visible(content: &[u8], config: &Config, out: &mut impl Write) -> MyResult<()> {
+    if !config.show_tabs && !config.show_nonprinting {
+        out.write_all(content)?;
+        return Ok(());
+    }
+
+    for &b in content {
+        match b {
+            b'\t' if config.show_tabs => out.write_all(b"^I")?,
+            b'\t' => out.write_all(b"\t")?,
+            _ if !config.show_nonprinting => out.write_all(&[b])?,
+            _ => {
+                let b = if b >= 128 {
+                    out.write_all(b"M-")?;
+                    b - 128
+                } else {
+                    b
+                };
+                match b {
+                    0..=31 => out.write_all(&[b'^', b + 64])?,
+                    127 => out.write_all(b"^?")?,
+                    _ => out.write_all(&[b])?,
                 }
             }
         }
     }
+
     Ok(())
 }
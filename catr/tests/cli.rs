@@ -11,6 +11,16 @@ const SPIDERS: &str = "tests/inputs/spiders.txt";
 
 const BUSTLE: &str = "tests/inputs/the-bustle.txt";
 
+const NONPRINTING: &str = "tests/inputs/nonprinting.txt";
+
+const BLANKS: &str = "tests/inputs/blanks.txt";
+
+const SPIDERS_GZ: &str = "tests/inputs/spiders.txt.gz";
+const SPIDERS_XZ: &str = "tests/inputs/spiders.txt.xz";
+const SPIDERS_ZST: &str = "tests/inputs/spiders.txt.zst";
+const SPIDERS_BZ2: &str = "tests/inputs/spiders.txt.bz2";
+const CORRUPT_GZ: &str = "tests/inputs/corrupt.txt.gz";
+
 #[test]
 fn usage() -> TestResult {
     for flag in &["-h", "--help"] {
@@ -23,12 +33,17 @@ fn usage() -> TestResult {
     Ok(())
 }
 
-#[test]
-fn skips_bad_file() -> TestResult {
+#[rstest]
+#[case(&[])]
+#[case(&["-n"])]
+#[case(&["--decompress"])]
+fn skips_bad_file(#[case] args: &[&str]) -> TestResult {
     let bad = gen_bad_file();
-    let expected = format!("{}: .* [(]os error 2[)]", bad);
+    // The file name is printed once, whichever way the file is opened.
+    let expected = format!("^{bad}: [^:]* [(]os error 2[)]\n$");
 
     Command::cargo_bin(PRG)?
+        .args(args)
         .arg(&bad)
         .assert()
         .success()
@@ -37,6 +52,20 @@ fn skips_bad_file() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn skips_corrupt_compressed_file() -> TestResult {
+    let expected = fs::read_to_string("tests/expected/spiders.txt.out")?;
+
+    Command::cargo_bin(PRG)?
+        .args(["--decompress", CORRUPT_GZ, SPIDERS_GZ])
+        .assert()
+        .success()
+        .stdout(expected)
+        .stderr(predicate::str::starts_with(CORRUPT_GZ));
+
+    Ok(())
+}
+
 #[rstest]
 #[case(BUSTLE, &["-"], "tests/expected/the-bustle.txt.stdin.out")]
 #[case(BUSTLE, &["-n", "-"], "tests/expected/the-bustle.txt.n.stdin.out")]
@@ -72,11 +101,38 @@ fn run_stdin(
 #[case(&[BUSTLE], "tests/expected/the-bustle.txt.out")]
 #[case(&["-n", BUSTLE], "tests/expected/the-bustle.txt.n.out")]
 #[case(&["-b", BUSTLE], "tests/expected/the-bustle.txt.b.out")]
+#[case(&[NONPRINTING], "tests/expected/nonprinting.txt.out")]
+#[case(&["-n", NONPRINTING], "tests/expected/nonprinting.txt.n.out")]
+#[case(&["-b", NONPRINTING], "tests/expected/nonprinting.txt.b.out")]
+#[case(&["-A", BUSTLE], "tests/expected/the-bustle.txt.A.out")]
+#[case(&["-A", NONPRINTING], "tests/expected/nonprinting.txt.A.out")]
+#[case(&["-vET", NONPRINTING], "tests/expected/nonprinting.txt.A.out")]
+#[case(&["-E", NONPRINTING], "tests/expected/nonprinting.txt.E.out")]
+#[case(&["-T", NONPRINTING], "tests/expected/nonprinting.txt.T.out")]
+#[case(&["-v", NONPRINTING], "tests/expected/nonprinting.txt.v.out")]
+#[case(&["-n", "--show-all", NONPRINTING], "tests/expected/nonprinting.txt.nA.out")]
+#[case(&["-s", BLANKS], "tests/expected/blanks.txt.s.out")]
+#[case(&["-s", "-n", BLANKS], "tests/expected/blanks.txt.sn.out")]
+#[case(&["--squeeze-blank", "-b", BLANKS], "tests/expected/blanks.txt.sb.out")]
 #[case(&[FOX, SPIDERS, BUSTLE], "tests/expected/all.out")]
 #[case(&[FOX, SPIDERS, BUSTLE, "-n"], "tests/expected/all.n.out")]
 #[case(&[FOX, SPIDERS, BUSTLE, "-b"], "tests/expected/all.b.out")]
+#[case(&[FOX, SPIDERS, BUSTLE, "--header"], "tests/expected/all.header.out")]
+#[case(&[FOX, SPIDERS, BUSTLE, "--separator", "----"], "tests/expected/all.separator.out")]
+#[case(&[FOX, SPIDERS, "-n", "--separator", "----"], "tests/expected/fox-spiders.n.separator.out")]
+#[case(&[FOX, EMPTY, SPIDERS, "--separator", "----"], "tests/expected/fox-empty-spiders.separator.out")]
+#[case(&[FOX, SPIDERS, "--header", "-n"], "tests/expected/fox-spiders.header.n.out")]
+#[case(&["--decompress", SPIDERS_GZ], "tests/expected/spiders.txt.out")]
+#[case(&["--decompress", SPIDERS_XZ], "tests/expected/spiders.txt.out")]
+#[case(&["--decompress", SPIDERS_ZST], "tests/expected/spiders.txt.out")]
+#[case(&["--decompress", "-n", SPIDERS_BZ2], "tests/expected/spiders.txt.n.out")]
+#[case(&["--decompress", SPIDERS], "tests/expected/spiders.txt.out")]
+#[case(&[FOX, SPIDERS, BUSTLE, "-n", "--number-per-file"], "tests/expected/all.n.per-file.out")]
+#[case(&[FOX, SPIDERS, BUSTLE, "-b", "--number-per-file"], "tests/expected/all.b.per-file.out")]
+#[case(&["-n", "--number-format=rz", "--number-width=3", "--number-separator=: ", "--starting-line-number=7", BUSTLE], "tests/expected/the-bustle.txt.nl-rz.out")]
+#[case(&["-n", "--number-format=ln", "--number-width=2", "--number-separator= ", BUSTLE], "tests/expected/the-bustle.txt.nl-ln.out")]
 fn run(#[case] args: &[&str], #[case] expected_file: &str) -> TestResult {
-    let expected = fs::read_to_string(expected_file)?;
+    let expected = fs::read(expected_file)?;
 
     Command::cargo_bin(PRG)?
         .args(args)
@@ -2,7 +2,7 @@ use std::error::Error;
 use std::fs::File;
 use std::io;
 use std::io::{BufRead, BufReader};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 pub type MyResult<T> = Result<T, Box<dyn Error>>;
@@ -20,6 +20,29 @@ pub fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     }
 }
 
+/// Like [`open`], but transparently decompresses files whose names end in `.gz`, `.xz`,
+/// `.zst` or `.bz2`. Any other file, including standard input, is read as is.
+/// Decompression errors are reported when reading from the returned reader.
+#[cfg(feature = "decompress")]
+pub fn open_decompressed(filename: &str) -> MyResult<Box<dyn BufRead>> {
+    use bzip2::bufread::MultiBzDecoder;
+    use flate2::bufread::MultiGzDecoder;
+    use xz2::bufread::XzDecoder;
+    use zstd::stream::read::Decoder as ZstdDecoder;
+
+    let file = open(filename)?;
+    let extension = std::path::Path::new(filename)
+        .extension()
+        .and_then(|e| e.to_str());
+    Ok(match extension {
+        Some("gz") => Box::new(BufReader::new(MultiGzDecoder::new(file))),
+        Some("xz") => Box::new(BufReader::new(XzDecoder::new_multi_decoder(file))),
+        Some("zst") => Box::new(BufReader::new(ZstdDecoder::with_buffer(file)?)),
+        Some("bz2") => Box::new(BufReader::new(MultiBzDecoder::new(file))),
+        _ => file,
+    })
+}
+
 pub struct LineIterator<T: BufRead> {
     file: T,
 }
@@ -54,7 +77,24 @@ pub fn find_files<'a, F>(
 where
     F: Fn(&PathBuf) -> bool + 'a + Copy,
 {
-    find_dir_entries(paths, recursive, move |p| p.is_file() && filter_func(p))
+    find_files_pruned(paths, recursive, |_| true, filter_func)
+}
+
+/// Like [`find_files`], but only descends into the directories below `paths` for which
+/// `enter_func` returns true, so the others are never read.
+pub fn find_files_pruned<'a, E, F>(
+    paths: &'a [String],
+    recursive: bool,
+    enter_func: E,
+    filter_func: F,
+) -> MyResult<Box<dyn Iterator<Item = PathBuf> + 'a>>
+where
+    E: Fn(&Path) -> bool + 'a + Copy,
+    F: Fn(&PathBuf) -> bool + 'a + Copy,
+{
+    walk(paths, recursive, enter_func, move |p| {
+        p.is_file() && filter_func(p)
+    })
 }
 
 pub fn find_dir_entries<'a, F>(
@@ -64,6 +104,19 @@ pub fn find_dir_entries<'a, F>(
 ) -> MyResult<Box<dyn Iterator<Item = PathBuf> + 'a>>
 where
     F: Fn(&PathBuf) -> bool + 'a + Copy,
+{
+    walk(paths, recursive, |_| true, filter_func)
+}
+
+fn walk<'a, E, F>(
+    paths: &'a [String],
+    recursive: bool,
+    enter_func: E,
+    filter_func: F,
+) -> MyResult<Box<dyn Iterator<Item = PathBuf> + 'a>>
+where
+    E: Fn(&Path) -> bool + 'a + Copy,
+    F: Fn(&PathBuf) -> bool + 'a + Copy,
 {
     // I have to use Box::new twice here since the types from each branch of if are different.
     // Wrapping in box ensures that the type is unified and the function signature is satisfied.
@@ -71,6 +124,9 @@ where
         Box::new(paths.iter().flat_map(move |p| {
             walkdir::WalkDir::new(p)
                 .into_iter()
+                .filter_entry(move |e| {
+                    e.depth() == 0 || !e.file_type().is_dir() || enter_func(e.path())
+                })
                 .filter_map(move |e| {
                     if e.is_err() {
                         eprintln!("{p}: {}", &e.unwrap_err());
@@ -0,0 +1,279 @@
+use std::cmp::Ordering;
+
+/// The order the input files are sorted in.
+#[derive(Debug, Eq, PartialEq)]
+pub enum Collation {
+    /// Byte by byte, like `LC_ALL=C sort`.
+    Bytes,
+    /// By the number at the start of the line, like `sort -n`.
+    Numeric,
+    /// By the version numbers in the line, like `sort -V`.
+    Version,
+    /// By the text between digits and the value of the digit runs.
+    Natural,
+}
+
+impl Collation {
+    /// Compares two lines. Lines that are equal in this order are compared byte by byte,
+    /// like the last resort comparison of `sort`, so only identical lines are equal.
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        let (a, b) = (a.as_bytes(), b.as_bytes());
+        let ordering = match self {
+            Collation::Bytes => Ordering::Equal,
+            Collation::Numeric => compare_numeric(a, b),
+            Collation::Version => compare_version(a, b),
+            Collation::Natural => compare_natural(a, b),
+        };
+        ordering.then_with(|| a.cmp(b))
+    }
+}
+
+/// A number at the start of a line: an optional minus sign, the integer part without
+/// leading zeros and the fraction without trailing zeros.
+struct Number<'a> {
+    negative: bool,
+    integer: &'a [u8],
+    fraction: &'a [u8],
+}
+
+impl<'a> Number<'a> {
+    /// Parses the number after any leading blanks. A line that doesn't start with a number
+    /// is zero, like in `sort -n`.
+    fn parse(line: &'a [u8]) -> Number<'a> {
+        let line = trim_start(line, |b| b == b' ' || b == b'\t');
+        let (negative, line) = match line.strip_prefix(b"-") {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let (integer, rest) = split_digits(line);
+        let fraction = match rest.strip_prefix(b".") {
+            Some(rest) => split_digits(rest).0,
+            None => &[],
+        };
+        let integer = trim_start(integer, |b| b == b'0');
+        let fraction = trim_end(fraction, |b| b == b'0');
+        Number {
+            // There's no negative zero.
+            negative: negative && !(integer.is_empty() && fraction.is_empty()),
+            integer,
+            fraction,
+        }
+    }
+}
+
+fn compare_numeric(a: &[u8], b: &[u8]) -> Ordering {
+    let (a, b) = (Number::parse(a), Number::parse(b));
+    let magnitude =
+        || compare_digits(a.integer, b.integer).then_with(|| a.fraction.cmp(b.fraction));
+    match (a.negative, b.negative) {
+        (false, false) => magnitude(),
+        (true, true) => magnitude().reverse(),
+        (negative, _) => {
+            if negative {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            }
+        }
+    }
+}
+
+/// Compares the values of two runs of digits, ignoring leading zeros.
+fn compare_digits(a: &[u8], b: &[u8]) -> Ordering {
+    let a = trim_start(a, |b| b == b'0');
+    let b = trim_start(b, |b| b == b'0');
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+/// Compares two lines like GNU `sort -V`, which treats them as file names: hidden files
+/// come first, and a suffix such as `.tar.gz` is only compared if the rest is equal.
+fn compare_version(a: &[u8], b: &[u8]) -> Ordering {
+    match (a, b) {
+        ([], []) => return Ordering::Equal,
+        ([], _) => return Ordering::Less,
+        (_, []) => return Ordering::Greater,
+        _ => {}
+    }
+    for special in [&b"."[..], b".."] {
+        match (a == special, b == special) {
+            (true, true) => return Ordering::Equal,
+            (true, false) => return Ordering::Less,
+            (false, true) => return Ordering::Greater,
+            (false, false) => {}
+        }
+    }
+    match (a[0] == b'.', b[0] == b'.') {
+        (true, false) => return Ordering::Less,
+        (false, true) => return Ordering::Greater,
+        _ => {}
+    }
+
+    let (a_prefix, b_prefix) = (without_suffix(a), without_suffix(b));
+    compare_version_parts(a_prefix, b_prefix).then_with(|| {
+        if a_prefix.len() == a.len() && b_prefix.len() == b.len() {
+            Ordering::Equal
+        } else {
+            compare_version_parts(a, b)
+        }
+    })
+}
+
+/// Returns `name` without its file suffix, the trailing run of `.` followed by a letter or
+/// `~` and then letters, digits or `~`.
+fn without_suffix(name: &[u8]) -> &[u8] {
+    let is_suffix_char = |b: u8| b.is_ascii_alphanumeric() || b == b'~';
+    let mut prefix_len = 0;
+    let mut i = 0;
+    while i < name.len() {
+        i += 1;
+        prefix_len = i;
+        while i + 1 < name.len()
+            && name[i] == b'.'
+            && (name[i + 1].is_ascii_alphabetic() || name[i + 1] == b'~')
+        {
+            i += 2;
+            while i < name.len() && is_suffix_char(name[i]) {
+                i += 1;
+            }
+        }
+    }
+    &name[..prefix_len]
+}
+
+/// Compares alternating runs of non-digits and digits. In the non-digits, `~` sorts before
+/// anything, even the end of the text, and letters sort before other characters. Runs of
+/// digits are compared by value.
+fn compare_version_parts(a: &[u8], b: &[u8]) -> Ordering {
+    let weight = |s: &[u8], i: usize| -> i32 {
+        match s.get(i) {
+            None => -1,
+            Some(b'~') => -2,
+            Some(c) if c.is_ascii_digit() => 0,
+            Some(c) if c.is_ascii_alphabetic() => i32::from(*c),
+            Some(c) => i32::from(*c) + 256,
+        }
+    };
+    let is_digit = |s: &[u8], i: usize| s.get(i).is_some_and(u8::is_ascii_digit);
+
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        while (i < a.len() && !is_digit(a, i)) || (j < b.len() && !is_digit(b, j)) {
+            let ordering = weight(a, i).cmp(&weight(b, j));
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+            i += 1;
+            j += 1;
+        }
+        let start = (i, j);
+        while is_digit(a, i) {
+            i += 1;
+        }
+        while is_digit(b, j) {
+            j += 1;
+        }
+        let ordering = compare_digits(&a[start.0..i], &b[start.1..j]);
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+/// Compares the runs of digits by value and the text between them byte by byte, so that
+/// `file9` sorts before `file10`.
+fn compare_natural(a: &[u8], b: &[u8]) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        let (a_text, a_rest) = split_non_digits(a);
+        let (b_text, b_rest) = split_non_digits(b);
+        let ordering = a_text.cmp(b_text);
+        if ordering != Ordering::Equal || (a_rest.is_empty() && b_rest.is_empty()) {
+            return ordering;
+        }
+        let (a_digits, a_rest) = split_digits(a_rest);
+        let (b_digits, b_rest) = split_digits(b_rest);
+        let ordering = compare_digits(a_digits, b_digits);
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+        (a, b) = (a_rest, b_rest);
+    }
+}
+
+/// Splits `s` after its leading digits.
+fn split_digits(s: &[u8]) -> (&[u8], &[u8]) {
+    s.split_at(s.iter().take_while(|b| b.is_ascii_digit()).count())
+}
+
+/// Splits `s` before its first digit.
+fn split_non_digits(s: &[u8]) -> (&[u8], &[u8]) {
+    s.split_at(s.iter().take_while(|b| !b.is_ascii_digit()).count())
+}
+
+fn trim_start(s: &[u8], f: impl Fn(u8) -> bool) -> &[u8] {
+    &s[s.iter().take_while(|&&b| f(b)).count()..]
+}
+
+fn trim_end(s: &[u8], f: impl Fn(u8) -> bool) -> &[u8] {
+    &s[..s.len() - s.iter().rev().take_while(|&&b| f(b)).count()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Collation;
+
+    /// Checks that `lines` are in increasing order.
+    fn assert_sorted(collation: Collation, lines: &[&str]) {
+        for pair in lines.windows(2) {
+            assert!(
+                collation.compare(pair[0], pair[1]).is_lt(),
+                "{:?} should sort before {:?}",
+                pair[0],
+                pair[1]
+            );
+            assert!(collation.compare(pair[1], pair[0]).is_gt());
+        }
+    }
+
+    #[test]
+    fn test_collation() {
+        assert_sorted(Collation::Bytes, &["10", "9", "B", "a"]);
+        assert_sorted(
+            Collation::Numeric,
+            &[
+                "-10",
+                "-2.5",
+                "-2",
+                "",
+                "-0 tie",
+                "0",
+                "0.05",
+                ".5",
+                "2",
+                "10",
+                "10 apples",
+            ],
+        );
+        assert_sorted(
+            Collation::Version,
+            &[
+                ".hidden",
+                "1.0~rc1",
+                "1.0",
+                "1.0a",
+                "1.0-1",
+                "1.2",
+                "1.10",
+                "app-1.9.tar.gz",
+                "app-1.10.tar.gz",
+            ],
+        );
+        assert_sorted(
+            Collation::Natural,
+            &["file", "file2", "file9", "file10", "file10a", "file010b"],
+        );
+
+        assert!(Collation::Numeric.compare("7", "7").is_eq());
+    }
+}
@@ -1,17 +1,67 @@
+mod collate;
+mod merge;
+
 use clap::{Arg, Command};
+use collate::Collation;
 use command_utils::{open, MyResult};
 use core::cmp::Ordering;
-use std::io::BufRead;
+use merge::Merge;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::num::NonZeroUsize;
+
+/// What to do when an input file isn't sorted.
+#[derive(Debug, Eq, PartialEq)]
+enum OrderCheck {
+    /// Report the first unsorted line of each file and fail once the output is complete,
+    /// like GNU comm.
+    Warn,
+    /// Fail at the first unsorted line.
+    Strict,
+    Off,
+}
+
+/// The lines printed by --set, instead of the columns.
+#[derive(Clone, Copy, Debug)]
+enum SetOperation {
+    /// Lines in any of the files.
+    Union,
+    /// Lines in all the files.
+    Intersection,
+    /// Lines of the first file that aren't in any of the others.
+    Difference,
+    /// Lines in exactly one of the files.
+    Symmetric,
+}
 
 #[derive(Debug)]
 pub struct Config {
-    file1: String,
-    file2: String,
+    files: Vec<String>,
     show_col1: bool,
     show_col2: bool,
     show_col3: bool,
     insensitive: bool,
+    collation: Collation,
+    /// The 1-based field the lines are compared on, instead of the whole line.
+    key: Option<usize>,
+    /// The character separating the fields, or `None` for runs of blanks.
+    field_separator: Option<char>,
+    changed: bool,
     delimiter: String,
+    check_order: OrderCheck,
+    total: bool,
+    zero_terminated: bool,
+    set: Option<SetOperation>,
+}
+
+/// One of the input files, read line by line while checking that it's sorted.
+struct Input {
+    /// The position of the file on the command line, used in error messages.
+    number: usize,
+    file: Box<dyn BufRead>,
+    prev: Option<String>,
+    line_number: usize,
+    /// Whether an unsorted line was found and reported.
+    unsorted: bool,
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -20,17 +70,13 @@ pub fn get_args() -> MyResult<Config> {
         .author("Krishna Addepalli <coolkrishna31@gmail.com>")
         .about("Rust comm")
         .arg(
-            Arg::new("file1")
-                .value_name("FILE1")
-                .help("Input file 1")
-                .num_args(1)
-                .required(true),
-        )
-        .arg(
-            Arg::new("file2")
-                .value_name("FILE2")
-                .help("Input file 2")
-                .num_args(1)
+            Arg::new("files")
+                .value_name("FILE")
+                .help(
+                    "Input files. Lines of more than two files are printed after a bitmap of \
+                    the files they're in, like 101",
+                )
+                .num_args(2..)
                 .required(true),
         )
         .arg(
@@ -61,103 +107,349 @@ pub fn get_args() -> MyResult<Config> {
         .arg(
             Arg::new("delimiter")
                 .value_name("DELIMITER")
-                .help("Output delimiter")
+                .help("Separate columns with DELIMITER. An empty DELIMITER separates them with NUL")
                 .short('d')
-                .long("delim")
+                .long("output-delimiter")
+                .visible_alias("delim")
                 .num_args(1)
                 .default_value("\t"),
         )
+        .arg(
+            Arg::new("check_order")
+                .help("Fail at the first unsorted line. By default, the first unsorted line of each file is reported and the exit status is 1 once the output is complete, like GNU comm")
+                .long("check-order")
+                .num_args(0)
+                .overrides_with("nocheck_order"),
+        )
+        .arg(
+            Arg::new("nocheck_order")
+                .help("Don't check that the input files are sorted")
+                .long("nocheck-order")
+                .num_args(0)
+                .overrides_with("check_order"),
+        )
+        .arg(
+            Arg::new("collation")
+                .value_name("ORDER")
+                .help("Order the input files are sorted in")
+                .long("collate")
+                .value_parser(["bytes", "numeric", "version", "natural"])
+                .default_value("bytes"),
+        )
+        .arg(
+            Arg::new("key")
+                .value_name("FIELD")
+                .help("Compare the lines on field FIELD, counting from 1")
+                .short('k')
+                .long("key")
+                .value_parser(clap::value_parser!(NonZeroUsize)),
+        )
+        .arg(
+            Arg::new("field_separator")
+                .value_name("CHAR")
+                .help("Fields are separated by CHAR instead of blanks")
+                .short('t')
+                .long("field-separator")
+                .value_parser(parse_separator)
+                .requires("key"),
+        )
+        .arg(
+            Arg::new("changed")
+                .help(
+                    "Report the lines only in FILE1 as removed, the ones only in FILE2 as added, \
+                    and the keys in both whose other fields differ as changed",
+                )
+                .long("changed")
+                .num_args(0)
+                .requires("key"),
+        )
+        .arg(
+            Arg::new("total")
+                .help("Print the number of lines in each column, followed by \"total\"")
+                .long("total")
+                .num_args(0),
+        )
+        .arg(
+            Arg::new("zero_terminated")
+                .help("Line delimiter is NUL, not newline")
+                .short('z')
+                .long("zero-terminated")
+                .num_args(0),
+        )
+        .arg(
+            Arg::new("set")
+                .value_name("OPERATION")
+                .help("Print the lines in the union, intersection, difference or symmetric difference of the files")
+                .long("set")
+                .value_parser(["union", "intersection", "difference", "symmetric"])
+                .conflicts_with_all(["show_col1", "show_col2", "show_col3", "changed", "total"]),
+        )
         .get_matches();
 
+    let files: Vec<String> = matches.get_many("files").unwrap().cloned().collect();
+    let columns_only = ["show_col1", "show_col2", "show_col3", "changed", "total"];
+    if files.len() > 2 && columns_only.iter().any(|id| matches.get_flag(id)) {
+        return Err(From::from(
+            "-1, -2, -3, --changed and --total only apply to two files",
+        ));
+    }
+
+    let delimiter = matches.get_one::<String>("delimiter").unwrap();
+
     Ok(Config {
-        file1: matches.get_one::<String>("file1").unwrap().to_string(),
-        file2: matches.get_one::<String>("file2").unwrap().to_string(),
+        files,
         show_col1: !matches.get_flag("show_col1"),
         show_col2: !matches.get_flag("show_col2"),
         show_col3: !matches.get_flag("show_col3"),
         insensitive: matches.get_flag("insensitive"),
-        delimiter: matches.get_one::<String>("delimiter").unwrap().to_string(),
+        collation: match matches.get_one::<String>("collation").unwrap().as_str() {
+            "bytes" => Collation::Bytes,
+            "numeric" => Collation::Numeric,
+            "version" => Collation::Version,
+            "natural" => Collation::Natural,
+            _ => unreachable!("Invalid collation"),
+        },
+        key: matches
+            .get_one::<NonZeroUsize>("key")
+            .map(|field| field.get()),
+        field_separator: matches.get_one::<char>("field_separator").copied(),
+        changed: matches.get_flag("changed"),
+        delimiter: match delimiter.as_str() {
+            "" => "\0".to_string(),
+            _ => delimiter.to_string(),
+        },
+        check_order: if matches.get_flag("check_order") {
+            OrderCheck::Strict
+        } else if matches.get_flag("nocheck_order") {
+            OrderCheck::Off
+        } else {
+            OrderCheck::Warn
+        },
+        total: matches.get_flag("total"),
+        zero_terminated: matches.get_flag("zero_terminated"),
+        set: matches
+            .get_one::<String>("set")
+            .map(|set| match set.as_str() {
+                "union" => SetOperation::Union,
+                "intersection" => SetOperation::Intersection,
+                "difference" => SetOperation::Difference,
+                "symmetric" => SetOperation::Symmetric,
+                _ => unreachable!("Invalid set operation"),
+            }),
     })
 }
 
+fn parse_separator(val: &str) -> Result<char, String> {
+    let mut chars = val.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(format!("\"{val}\" must be a single character")),
+    }
+}
+
 pub fn run(config: Config) -> MyResult<()> {
-    let file1 = &config.file1;
-    let file2 = &config.file2;
+    if config.files.iter().filter(|file| *file == "-").count() > 1 {
+        return Err(From::from("Only one input file can be STDIN (\"-\")"));
+    }
 
-    if file1 == "-" && file2 == "-" {
-        return Err(From::from("Both input files cannot be STDIN (\"-\")"));
+    let inputs = config
+        .files
+        .iter()
+        .enumerate()
+        .map(|(i, file)| Ok(Input::new(i + 1, open(file)?)))
+        .collect::<MyResult<Vec<_>>>()?;
+    let mut merge = Merge::new(inputs, &config)?;
+    let mut out = BufWriter::new(io::stdout());
+    if config.files.len() == 2 && config.set.is_none() {
+        print_columns(&mut merge, &config, &mut out)?;
+    } else {
+        print_memberships(&mut merge, &config, &mut out)?;
     }
+    out.flush()?;
 
-    let case = |line: String| {
-        if config.insensitive {
-            line.to_lowercase()
-        } else {
-            line
+    if merge.unsorted() {
+        return Err(From::from("input is not in sorted order"));
+    }
+    Ok(())
+}
+
+/// Prints the lines of two files in three columns: the lines only in the first file, the
+/// ones only in the second and the ones in both.
+fn print_columns(merge: &mut Merge, config: &Config, out: &mut impl Write) -> MyResult<()> {
+    let terminator = terminator(config);
+    // The number of lines in each column, including the ones not shown.
+    let mut counts = [0; 3];
+
+    // Prints `line` in column `col`, indented by the columns before it that are shown. The
+    // --changed report labels the lines instead.
+    let mut print = |col: usize, line: &str| -> io::Result<()> {
+        counts[col - 1] += 1;
+        let shown = [config.show_col1, config.show_col2, config.show_col3];
+        if shown[col - 1] {
+            if config.changed {
+                let label = ["removed", "added", "changed"][col - 1];
+                write!(out, "{label}{}{line}{terminator}", config.delimiter)?;
+            } else {
+                let indent = shown[..col - 1].iter().filter(|&&s| s).count();
+                write!(out, "{}{line}{terminator}", config.delimiter.repeat(indent))?;
+            }
         }
+        Ok(())
     };
-    let mut lines1 = open(file1)?.lines().map_while(Result::ok).map(case);
-    let mut lines2 = open(file2)?.lines().map_while(Result::ok).map(case);
-
-    let mut line1 = lines1.next();
-    let mut line2 = lines2.next();
-    loop {
-        let mut print_line: Vec<String> = vec![];
-        match (&mut line1, &mut line2) {
-            (Some(l1), Some(l2)) => match l1.cmp(&l2) {
-                Ordering::Equal => {
-                    if config.show_col3 {
-                        if config.show_col1 {
-                            print_line.push("".to_string());
-                        }
-                        if config.show_col2 {
-                            print_line.push("".to_string());
-                        }
-
-                        print_line.push(l1.to_string());
-                    }
-                    line1 = lines1.next();
-                    line2 = lines2.next();
-                }
-                Ordering::Less => {
-                    if config.show_col1 {
-                        print_line.push(l1.to_string());
-                    }
 
-                    line1 = lines1.next();
+    while let Some(group) = merge.next_group()? {
+        match group.as_slice() {
+            [(_, l1), (_, l2)] => {
+                if !config.changed {
+                    print(3, l1)?;
+                } else if other_fields(l1, config).ne(other_fields(l2, config)) {
+                    print(3, &format!("{l1}{}{l2}", config.delimiter))?;
                 }
-                Ordering::Greater => {
-                    if config.show_col2 {
-                        if config.show_col1 {
-                            print_line.push("".to_string());
-                        }
-                        print_line.push(l2.to_string());
-                    }
-                    line2 = lines2.next();
-                }
-            },
-            (Some(l1), None) => {
-                if config.show_col1 {
-                    print_line.push(l1.to_string());
-                }
-                line1 = lines1.next();
             }
-            (None, Some(l2)) => {
-                if config.show_col1 {
-                    print_line.push("".to_string());
-                }
-                if config.show_col2 {
-                    print_line.push(l2.to_string());
+            [(0, l1)] => print(1, l1)?,
+            [(_, l2)] => print(2, l2)?,
+            _ => unreachable!("Invalid group"),
+        }
+    }
+
+    if config.total {
+        let d = &config.delimiter;
+        write!(
+            out,
+            "{}{d}{}{d}{}{d}total{terminator}",
+            counts[0], counts[1], counts[2]
+        )?;
+    }
+    Ok(())
+}
+
+/// Prints the lines selected by --set, or else every line after a bitmap of the files it's
+/// in.
+fn print_memberships(merge: &mut Merge, config: &Config, out: &mut impl Write) -> MyResult<()> {
+    let terminator = terminator(config);
+    let num_files = config.files.len();
+    while let Some(group) = merge.next_group()? {
+        // Equal lines may differ in case or outside of the key, so print the first one.
+        let (first, line) = &group[0];
+        match config.set {
+            Some(set) => {
+                let selected = match set {
+                    SetOperation::Union => true,
+                    SetOperation::Intersection => group.len() == num_files,
+                    SetOperation::Difference => group.len() == 1 && *first == 0,
+                    SetOperation::Symmetric => group.len() == 1,
+                };
+                if selected {
+                    write!(out, "{line}{terminator}")?;
                 }
-                line2 = lines2.next();
             }
-            (None, None) => {
-                break;
+            None => {
+                let mut bitmap = vec!['0'; num_files];
+                for (index, _) in &group {
+                    bitmap[*index] = '1';
+                }
+                let bitmap: String = bitmap.into_iter().collect();
+                write!(out, "{bitmap}{}{line}{terminator}", config.delimiter)?;
             }
         }
-        if !print_line.is_empty() {
-            println!("{}", print_line.join(&config.delimiter));
+    }
+    Ok(())
+}
+
+fn terminator(config: &Config) -> char {
+    if config.zero_terminated {
+        '\0'
+    } else {
+        '\n'
+    }
+}
+
+/// Compares the keys of two lines in the order given by `--collate`, ignoring case with `-i`.
+fn compare(a: &str, b: &str, config: &Config) -> Ordering {
+    let (a, b) = (key(a, config), key(b, config));
+    if config.insensitive {
+        config
+            .collation
+            .compare(&a.to_lowercase(), &b.to_lowercase())
+    } else {
+        config.collation.compare(a, b)
+    }
+}
+
+/// Returns the field of `line` selected by `--key`, or the whole line without one. A line
+/// with too few fields has an empty key.
+fn key<'a>(line: &'a str, config: &Config) -> &'a str {
+    let Some(field) = config.key else {
+        return line;
+    };
+    let field = match config.field_separator {
+        Some(separator) => line.split(separator).nth(field - 1),
+        None => line.split_whitespace().nth(field - 1),
+    };
+    field.unwrap_or("")
+}
+
+/// Returns the fields of `line` besides the one selected by `--key`, which --changed compares
+/// exactly, as the keys may be equal without being identical.
+fn other_fields<'a>(line: &'a str, config: &'a Config) -> impl Iterator<Item = &'a str> {
+    let field = config.key.unwrap_or(0);
+    let fields: Box<dyn Iterator<Item = &str>> = match config.field_separator {
+        Some(separator) => Box::new(line.split(separator)),
+        None => Box::new(line.split_whitespace()),
+    };
+    fields
+        .enumerate()
+        .filter(move |(i, _)| i + 1 != field)
+        .map(|(_, f)| f)
+}
+
+impl Input {
+    fn new(number: usize, file: Box<dyn BufRead>) -> Input {
+        Input {
+            number,
+            file,
+            prev: None,
+            line_number: 0,
+            unsorted: false,
         }
     }
 
-    Ok(())
+    /// Returns the next line without its newline, or `None` at the end of the file. The
+    /// first line that sorts before the previous one is reported as requested by
+    /// `config.check_order`.
+    fn next(&mut self, config: &Config) -> MyResult<Option<String>> {
+        let terminator = if config.zero_terminated { b'\0' } else { b'\n' };
+        let mut buf = Vec::new();
+        if self.file.read_until(terminator, &mut buf)? == 0 {
+            return Ok(None);
+        }
+        if buf.last() == Some(&terminator) {
+            buf.pop();
+        }
+        let line = String::from_utf8(buf).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            )
+        })?;
+        self.line_number += 1;
+
+        if config.check_order != OrderCheck::Off && !self.unsorted {
+            if let Some(prev) = &self.prev {
+                if compare(prev, &line, config) == Ordering::Greater {
+                    let msg = format!(
+                        "file {} is not in sorted order at line {}",
+                        self.number, self.line_number
+                    );
+                    if config.check_order == OrderCheck::Strict {
+                        return Err(From::from(msg));
+                    }
+                    eprintln!("{msg}");
+                    self.unsorted = true;
+                }
+            }
+            self.prev = Some(line.clone());
+        }
+        Ok(Some(line))
+    }
 }
@@ -0,0 +1,95 @@
+use super::{compare, Config, Input};
+use command_utils::MyResult;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// Merges the lines of sorted inputs, grouping the lines that compare equal.
+pub struct Merge<'a> {
+    inputs: Vec<Input>,
+    /// The next line of each input that isn't exhausted.
+    heap: BinaryHeap<Head<'a>>,
+    config: &'a Config,
+}
+
+/// The next line of the input at `index`, ordered so that the heap pops the smallest line
+/// first, and equal lines in the order of their inputs.
+struct Head<'a> {
+    line: String,
+    index: usize,
+    config: &'a Config,
+}
+
+impl PartialEq for Head<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Head<'_> {}
+
+impl PartialOrd for Head<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Head<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare(&other.line, &self.line, self.config).then(other.index.cmp(&self.index))
+    }
+}
+
+impl<'a> Merge<'a> {
+    pub fn new(inputs: Vec<Input>, config: &'a Config) -> MyResult<Merge<'a>> {
+        let mut merge = Merge {
+            inputs,
+            heap: BinaryHeap::new(),
+            config,
+        };
+        for index in 0..merge.inputs.len() {
+            merge.advance(index)?;
+        }
+        Ok(merge)
+    }
+
+    fn advance(&mut self, index: usize) -> MyResult<()> {
+        if let Some(line) = self.inputs[index].next(self.config)? {
+            self.heap.push(Head {
+                line,
+                index,
+                config: self.config,
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns the next group of equal lines with the index of the input each one comes
+    /// from, in the order of the inputs. A group has at most one line of each input, so a
+    /// line repeated in an input pairs up with as many copies in the others, like in comm.
+    pub fn next_group(&mut self) -> MyResult<Option<Vec<(usize, String)>>> {
+        let Some(first) = self.heap.pop() else {
+            return Ok(None);
+        };
+        let mut group = vec![first];
+        while let Some(head) = self.heap.peek() {
+            if compare(&group[0].line, &head.line, self.config) != Ordering::Equal {
+                break;
+            }
+            group.push(self.heap.pop().unwrap());
+        }
+        for head in &group {
+            self.advance(head.index)?;
+        }
+        Ok(Some(
+            group
+                .into_iter()
+                .map(|head| (head.index, head.line))
+                .collect(),
+        ))
+    }
+
+    /// Whether an unsorted line was found in any of the inputs.
+    pub fn unsorted(&self) -> bool {
+        self.inputs.iter().any(|input| input.unsorted)
+    }
+}
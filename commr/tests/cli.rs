@@ -8,7 +8,20 @@ const PRG: &str = "commr";
 const EMPTY: &str = "tests/inputs/empty.txt";
 const FILE1: &str = "tests/inputs/file1.txt";
 const FILE2: &str = "tests/inputs/file2.txt";
+const FILE3: &str = "tests/inputs/file3.txt";
 const BLANK: &str = "tests/inputs/blank.txt";
+const BAR: &str = "tests/inputs/bar.txt";
+const MIXED_CASE: &str = "tests/inputs/mixed_case.txt";
+const NUMBERS1: &str = "tests/inputs/numbers1.txt";
+const NUMBERS2: &str = "tests/inputs/numbers2.txt";
+const VERSIONS1: &str = "tests/inputs/versions1.txt";
+const VERSIONS2: &str = "tests/inputs/versions2.txt";
+const USERS1: &str = "tests/inputs/users1.csv";
+const USERS2: &str = "tests/inputs/users2.csv";
+const NAMES1: &str = "tests/inputs/names1.csv";
+const NAMES2: &str = "tests/inputs/names2.csv";
+const FILE1_ZERO: &str = "tests/inputs/file1_zero.txt";
+const FILE2_ZERO: &str = "tests/inputs/file2_zero.txt";
 
 #[test]
 fn dies_no_args() -> TestResult {
@@ -45,17 +58,89 @@ fn dies(
     Ok(())
 }
 
-#[test]
-fn dies_both_stdin() -> TestResult {
-    let expected = "Both input files cannot be STDIN (\"-\")";
+#[rstest]
+#[case(&["-", "-"])]
+#[case(&[FILE1, "-", "-"])]
+fn dies_both_stdin(#[case] args: &[&str]) -> TestResult {
+    let expected = "Only one input file can be STDIN (\"-\")";
     Command::cargo_bin(PRG)?
-        .args(&["-", "-"])
+        .args(args)
         .assert()
         .failure()
         .stderr(predicate::str::contains(expected));
     Ok(())
 }
 
+#[rstest]
+#[case(&["--changed", USERS1, USERS2], "required arguments were not provided")]
+#[case(&["-t", ",", USERS1, USERS2], "required arguments were not provided")]
+#[case(&["-k", "0", USERS1, USERS2], "invalid value '0'")]
+#[case(&["-k", "1", "-t", ",,", USERS1, USERS2], "\",,\" must be a single character")]
+#[case(&["-1", FILE1, FILE2, FILE3], "only apply to two files")]
+#[case(&["--total", FILE1, FILE2, FILE3], "only apply to two files")]
+#[case(&["--set=union", "-3", FILE1, FILE2], "cannot be used with")]
+#[case(&["--set=union", FILE1], "2 values required")]
+fn dies_invalid_args(#[case] args: &[&str], #[case] expected: &str) -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(expected));
+
+    Ok(())
+}
+
+#[test]
+fn warns_unsorted() -> TestResult {
+    let expected = fs::read_to_string("tests/expected/bar_file1.out")?;
+    // Like GNU comm, the whole output is printed but the exit status is still 1.
+    Command::cargo_bin(PRG)?
+        .args([BAR, FILE1])
+        .assert()
+        .code(1)
+        .stdout(expected)
+        .stderr(predicate::str::contains(
+            "file 1 is not in sorted order at line 2",
+        ))
+        .stderr(predicate::str::contains("input is not in sorted order"));
+
+    Ok(())
+}
+
+#[rstest]
+#[case(&["--check-order", FILE1, BAR], "file 2 is not in sorted order at line 2")]
+#[case(&["--check-order", MIXED_CASE, FILE1], "file 1 is not in sorted order at line 2")]
+#[case(&["--nocheck-order", "--check-order", BAR, FILE1], "file 1 is not in sorted order")]
+#[case(&["--check-order", NUMBERS1, NUMBERS2], "file 1 is not in sorted order at line 3")]
+#[case(&["--check-order", "--collate=natural", VERSIONS1, VERSIONS2], "file 1 is not in sorted order at line 2")]
+fn dies_unsorted(#[case] args: &[&str], #[case] expected: &str) -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(expected))
+        .stderr(predicate::str::contains("input is not in sorted order").not());
+
+    Ok(())
+}
+
+#[rstest]
+#[case(&["--nocheck-order", BAR, FILE1])]
+#[case(&["--check-order", "--nocheck-order", BAR, FILE1])]
+// Sorted when case is ignored.
+#[case(&["--check-order", "-i", MIXED_CASE, FILE1])]
+#[case(&["--check-order", "--collate=numeric", "-i", MIXED_CASE, FILE1])]
+#[case(&["--check-order", "--collate=natural", NUMBERS1, NUMBERS2])]
+fn allows_unsorted(#[case] args: &[&str]) -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .assert()
+        .success()
+        .stderr("");
+
+    Ok(())
+}
+
 #[rstest]
 #[case(&[EMPTY, EMPTY], "tests/expected/empty_empty.out")]
 #[case(&[FILE1, FILE1], "tests/expected/file1_file1.out")]
@@ -85,6 +170,23 @@ fn dies_both_stdin() -> TestResult {
 #[case(&[FILE1, FILE2, "-23", "-d", ":"], "tests/expected/file1_file2.23.delim.out")]
 #[case(&[FILE1, FILE2, "-123", "-d", ":"], "tests/expected/file1_file2.123.delim.out")]
 #[case(&[BLANK, FILE1], "tests/expected/blank_file1.out")]
+#[case(&["--total", FILE1, FILE2], "tests/expected/file1_file2.total.out")]
+#[case(&["--total", "-12", "--output-delimiter=::", FILE1, FILE2], "tests/expected/file1_file2.12.total.delim.out")]
+#[case(&["--output-delimiter=", FILE1, FILE2], "tests/expected/file1_file2.nul_delim.out")]
+#[case(&["--delim", ":", FILE1, FILE2], "tests/expected/file1_file2.delim.out")]
+#[case(&["-z", FILE1_ZERO, FILE2_ZERO], "tests/expected/file1_file2.zero.out")]
+#[case(&["--collate=numeric", NUMBERS1, NUMBERS2], "tests/expected/numbers1_numbers2.numeric.out")]
+#[case(&["--collate=version", VERSIONS1, VERSIONS2], "tests/expected/versions1_versions2.version.out")]
+#[case(&["-k", "1", "-t", ",", USERS1, USERS2], "tests/expected/users1_users2.key.out")]
+#[case(&["-k1", "-t,", "--changed", USERS1, USERS2], "tests/expected/users1_users2.changed.out")]
+#[case(&["-k1", "-t,", "--changed", "-3", "--total", USERS1, USERS2], "tests/expected/users1_users2.changed.3.total.out")]
+#[case(&["-i", "-k1", "-t,", "--changed", NAMES1, NAMES2], "tests/expected/names1_names2.i.changed.out")]
+#[case(&[FILE1, FILE2, FILE3], "tests/expected/file1_file2_file3.out")]
+#[case(&["--set=union", FILE1, FILE2, FILE3], "tests/expected/file1_file2_file3.union.out")]
+#[case(&["--set=intersection", FILE1, FILE2, FILE3], "tests/expected/file1_file2_file3.intersection.out")]
+#[case(&["--set=difference", FILE1, FILE2, FILE3], "tests/expected/file1_file2_file3.difference.out")]
+#[case(&["--set=symmetric", FILE1, FILE2, FILE3], "tests/expected/file1_file2_file3.symmetric.out")]
+#[case(&["--set=difference", FILE1, FILE2], "tests/expected/file1_file2.difference.out")]
 fn run(#[case] args: &[&str], #[case] expected: &str) -> TestResult {
     let expected = fs::read_to_string(expected)?;
     Command::cargo_bin(PRG)?
@@ -99,6 +201,7 @@ fn run(#[case] args: &[&str], #[case] expected: &str) -> TestResult {
 #[rstest]
 #[case(&["-123", "-i", "-", FILE2], FILE1, "tests/expected/file1_file2.123.i.out")]
 #[case(&["-123", "-i", "-", FILE1], FILE2, "tests/expected/file1_file2.123.i.out")]
+#[case(&[FILE1, "-", FILE3], FILE2, "tests/expected/file1_file2_file3.out")]
 fn run_stdin(
     #[case] args: &[&str],
     #[case] input_file: &str,
@@ -25,4 +25,18 @@ for POS in 1 2 8 1-2 2-3 1-8; do
   cut -c $POS $CSV > "$OUT_DIR/$(basename $CSV).c${POS}.out"
 done
 
-echo -e "AA\nÉÉ\nSS\nJJ" > "$OUT_DIR/books.c1,1.out"
+for POS in 2- -2 2,1 1,3-; do
+  cut -f $POS $TSV > "$OUT_DIR/$(basename $TSV).f${POS}.out"
+  cut -c $POS $TSV > "$OUT_DIR/$(basename $TSV).c${POS}.out"
+  cut -f $POS --complement $TSV > "$OUT_DIR/$(basename $TSV).f${POS}.complement.out"
+  cut -c $POS --complement $TSV > "$OUT_DIR/$(basename $TSV).c${POS}.complement.out"
+done
+
+echo -e "A\nÉ\nS\nJ" > "$OUT_DIR/books.c1,1.out"
+echo -e "AA\nÉÉ\nSS\nJJ" > "$OUT_DIR/books.c1,1.preserve.out"
+
+RAGGED="tests/inputs/ragged.tsv"
+cut -f 2- $RAGGED > "$OUT_DIR/$(basename $RAGGED).f2-.out"
+cut -s -f 2- $RAGGED > "$OUT_DIR/$(basename $RAGGED).f2-.s.out"
+cut -f 1,3 --output-delimiter=' | ' $RAGGED > "$OUT_DIR/$(basename $RAGGED).f1,3.odelim.out"
+cut -d , -f 3 tests/inputs/books.csv > "$OUT_DIR/books.csv.f3.dcomma.out"
@@ -0,0 +1,89 @@
+use crate::range_parser::parse_pos;
+use crate::PositionList;
+use command_utils::MyResult;
+use csv::StringRecord;
+use globset::{Glob, GlobMatcher};
+
+/// A column of a CSV file, selected by position or by a header name that may be a glob.
+#[derive(Debug)]
+pub(super) enum Column {
+    Positions(PositionList),
+    Name(String, GlobMatcher),
+}
+
+/// Parses a comma-separated list of columns. Unless `names_only` is set, items that are
+/// positions or ranges select columns by position and the others are header names.
+pub(super) fn parse_columns(list: &str, names_only: bool) -> MyResult<Vec<Column>> {
+    list.split(',')
+        .map(|item| {
+            if !names_only {
+                if let Ok(positions) = parse_pos(item) {
+                    return Ok(Column::Positions(positions));
+                }
+            }
+            if item.is_empty() {
+                return Err(From::from("Column name cannot be empty"));
+            }
+            let glob =
+                Glob::new(item).map_err(|e| format!("invalid column name \"{item}\": {e}"))?;
+            Ok(Column::Name(item.to_string(), glob.compile_matcher()))
+        })
+        .collect()
+}
+
+/// Returns the positions of `columns` in a file with `headers`. A glob selects all the
+/// columns it matches, in the order of the header.
+pub(super) fn resolve_columns(
+    columns: &[Column],
+    headers: &StringRecord,
+) -> MyResult<PositionList> {
+    let mut positions = Vec::new();
+    for column in columns {
+        match column {
+            Column::Positions(list) => positions.extend(list.iter().cloned()),
+            Column::Name(name, glob) => {
+                let start = positions.len();
+                positions.extend(
+                    headers
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, header)| glob.is_match(header))
+                        .map(|(i, _)| i..i + 1),
+                );
+                if positions.len() == start {
+                    return Err(From::from(format!("no column named \"{name}\"")));
+                }
+            }
+        }
+    }
+    Ok(positions)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::{parse_columns, resolve_columns};
+    use csv::StringRecord;
+
+    #[test]
+    fn test_resolve_columns() {
+        let headers = StringRecord::from(vec!["id", "name", "email", "created_at", "created_by"]);
+        let resolve = |list: &str, names_only: bool| {
+            let columns = parse_columns(list, names_only);
+            assert!(columns.is_ok());
+            resolve_columns(&columns.unwrap(), &headers).map_err(|e| e.to_string())
+        };
+
+        assert_eq!(resolve("email,name", true), Ok(vec![2..3, 1..2]));
+        assert_eq!(resolve("id,created_*", false), Ok(vec![0..1, 3..4, 4..5]));
+        assert_eq!(resolve("2-3,id", false), Ok(vec![1..3, 0..1]));
+        // With -F, numbers are names too.
+        assert_eq!(resolve("1", true), Err("no column named \"1\"".to_string()));
+        assert_eq!(
+            resolve("name,phone", true),
+            Err("no column named \"phone\"".to_string())
+        );
+
+        assert!(parse_columns("name,", true).is_err());
+        assert!(parse_columns("[a-", true).is_err());
+    }
+}
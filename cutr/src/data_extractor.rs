@@ -1,5 +1,4 @@
 use core::ops::Range;
-use csv::StringRecord;
 
 pub(super) fn extract_chars(line: &str, char_pos: &[Range<usize>]) -> String {
     let mut res = String::new();
@@ -36,20 +35,16 @@ pub(super) fn extract_bytes(line: &str, byte_pos: &[Range<usize>]) -> String {
     String::from_utf8_lossy(&res).to_string()
 }
 
-pub(super) fn extract_fields<'a>(
-    line: &'a StringRecord,
-    field_pos: &[Range<usize>],
-) -> Vec<&'a str> {
+pub(super) fn extract_fields<'a>(fields: &[&'a str], field_pos: &[Range<usize>]) -> Vec<&'a str> {
     field_pos
         .iter()
         .cloned()
-        .flat_map(|range| range.filter_map(|i| line.get(i)))
+        .flat_map(|range| range.take_while(|&i| i < fields.len()).map(|i| fields[i]))
         .collect()
 }
 #[cfg(test)]
 mod unit_tests {
     use super::{extract_bytes, extract_chars, extract_fields};
-    use csv::StringRecord;
     #[test]
     fn test_extract_chars() {
         assert_eq!(extract_chars("", &[0..1]), "".to_string());
@@ -71,11 +66,12 @@ mod unit_tests {
 
     #[test]
     fn test_extract_fields() {
-        let rec = StringRecord::from(vec!["Captain", "Sham", "12345"]);
+        let rec = ["Captain", "Sham", "12345"];
         assert_eq!(extract_fields(&rec, &[0..1]), &["Captain"]);
         assert_eq!(extract_fields(&rec, &[1..2]), &["Sham"]);
         assert_eq!(extract_fields(&rec, &[0..1, 2..3]), &["Captain", "12345"]);
         assert_eq!(extract_fields(&rec, &[0..1, 3..4]), &["Captain"]);
         assert_eq!(extract_fields(&rec, &[1..2, 0..1]), &["Sham", "Captain"]);
+        assert_eq!(extract_fields(&rec, &[1..usize::MAX]), &["Sham", "12345"]);
     }
 }
@@ -1,11 +1,13 @@
+mod columns;
 mod data_extractor;
 mod range_parser;
 
-use clap::{Arg, ArgMatches, Command};
+use clap::{parser::ValueSource, Arg, ArgMatches, Command};
+use columns::{parse_columns, resolve_columns, Column};
 use command_utils::{open, MyResult};
 use csv::{ReaderBuilder, WriterBuilder};
 use data_extractor::{extract_bytes, extract_chars, extract_fields};
-use range_parser::parse_pos;
+use range_parser::{arrange, parse_pos, Selection};
 use std::io;
 use std::io::BufRead;
 use std::ops::Range;
@@ -16,13 +18,19 @@ enum Extract {
     Fields(PositionList),
     Bytes(PositionList),
     Chars(PositionList),
+    /// Fields of CSV files selected by header name, resolved for each file.
+    Columns(Vec<Column>, Selection),
 }
 
 #[derive(Debug)]
 pub struct Config {
     files: Vec<String>,
     delimiter: u8,
+    output_delimiter: String,
     extract: Extract,
+    /// Parse the fields as CSV, with quoting, instead of splitting lines on the delimiter.
+    csv: bool,
+    only_delimited: bool,
 }
 pub fn get_args() -> MyResult<Config> {
     let matches = Command::new("cutr")
@@ -42,8 +50,9 @@ pub fn get_args() -> MyResult<Config> {
                 .help("Selected bytes")
                 .short('b')
                 .long("bytes")
-                .num_args(0..)
-                .conflicts_with_all(["chars", "fields"]),
+                .num_args(1)
+                .allow_hyphen_values(true)
+                .conflicts_with_all(["chars", "fields", "field_names"]),
         )
         .arg(
             Arg::new("chars")
@@ -51,8 +60,9 @@ pub fn get_args() -> MyResult<Config> {
                 .help("Selected characters")
                 .short('c')
                 .long("chars")
-                .num_args(0..)
-                .conflicts_with_all(["bytes", "fields"]),
+                .num_args(1)
+                .allow_hyphen_values(true)
+                .conflicts_with_all(["bytes", "fields", "field_names"]),
         )
         .arg(
             Arg::new("fields")
@@ -60,21 +70,82 @@ pub fn get_args() -> MyResult<Config> {
                 .help("Selected fields")
                 .short('f')
                 .long("fields")
-                .num_args(0..)
-                .conflicts_with_all(["chars", "bytes"]),
+                .num_args(1)
+                .allow_hyphen_values(true)
+                .conflicts_with_all(["chars", "bytes", "field_names"]),
+        )
+        .arg(
+            Arg::new("field_names")
+                .value_name("NAMES")
+                .help("Selected fields by header name, in the order given. A glob like created_* selects the fields it matches in the order of the header")
+                .short('F')
+                .long("field-names")
+                .num_args(1)
+                .conflicts_with_all(["chars", "bytes", "fields"])
+                .requires("csv"),
         )
         .arg(
             Arg::new("delim")
                 .value_name("DELIMITER")
-                .help("Field delimiter")
+                .help("Field delimiter, a comma by default with --csv")
                 .short('d')
                 .long("delim")
                 .num_args(1)
                 .default_value("\t"),
         )
+        .arg(
+            Arg::new("output_delimiter")
+                .value_name("STRING")
+                .help("Separate the output fields with STRING instead of the field delimiter")
+                .long("output-delimiter")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("only_delimited")
+                .help("Don't print the lines without the field delimiter")
+                .short('s')
+                .long("only-delimited")
+                .num_args(0),
+        )
+        .arg(
+            Arg::new("csv")
+                .help("Read the fields as CSV, where delimiters in quoted fields are kept")
+                .long("csv")
+                .num_args(0),
+        )
+        .arg(
+            Arg::new("complement")
+                .help("Select the bytes, characters or fields that aren't in the list")
+                .long("complement")
+                .num_args(0),
+        )
+        .arg(
+            Arg::new("preserve_order")
+                .help("Print the ranges in the order given, repeating overlapping ones")
+                .long("preserve-order")
+                .num_args(0)
+                .conflicts_with("complement"),
+        )
         .get_matches();
 
     let delimiter = parse_delimiter(&matches)?;
+    let output_delimiter = match matches.get_one::<String>("output_delimiter") {
+        Some(output_delimiter) => output_delimiter.to_owned(),
+        None => (delimiter as char).to_string(),
+    };
+    let csv = matches.get_flag("csv");
+    let field_only =
+        matches.contains_id("output_delimiter") || matches.get_flag("only_delimited") || csv;
+    if field_only && !matches.contains_id("fields") && !matches.contains_id("field_names") {
+        return Err(From::from(
+            "--output-delimiter, --only-delimited and --csv only apply to --fields",
+        ));
+    }
+    if csv && output_delimiter.len() != 1 {
+        return Err(From::from(format!(
+            "--output-delimiter \"{output_delimiter}\" must be a single byte with --csv"
+        )));
+    }
     let extract = parse_fields_bytes_or_chars(&matches)?;
     Ok(Config {
         files: matches
@@ -83,29 +154,57 @@ pub fn get_args() -> MyResult<Config> {
             .map(|f| f.to_owned())
             .collect(),
         delimiter,
+        output_delimiter,
         extract,
+        csv,
+        only_delimited: matches.get_flag("only_delimited"),
     })
 }
 
 fn parse_fields_bytes_or_chars(matches: &ArgMatches) -> MyResult<Extract> {
     let fields = matches.get_one::<String>("fields");
+    let field_names = matches.get_one::<String>("field_names");
     let bytes = matches.get_one::<String>("bytes");
     let chars = matches.get_one::<String>("chars");
 
-    if fields.is_none() && bytes.is_none() && chars.is_none() {
+    if fields.is_none() && field_names.is_none() && bytes.is_none() && chars.is_none() {
         return Err(From::from("Must have --fields, --bytes, or --chars"));
     }
 
-    let extract = if fields.is_some() && bytes.is_none() && chars.is_none() {
-        Extract::Fields(parse_pos(fields.unwrap())?)
-    } else if fields.is_none() && bytes.is_some() && chars.is_none() {
-        Extract::Bytes(parse_pos(bytes.unwrap())?)
-    } else if fields.is_none() && bytes.is_none() && chars.is_some() {
-        Extract::Chars(parse_pos(chars.unwrap())?)
+    let selection = if matches.get_flag("complement") {
+        Selection::Complement
+    } else if matches.get_flag("preserve_order") {
+        Selection::AsGiven
     } else {
-        return Err(From::from(
-            "Only one option of --fields, --bytes, or --chars is accepted",
+        Selection::Sorted
+    };
+    // Columns picked by name are printed in the order given, like the columns of csvcut.
+    let column_selection = match selection {
+        Selection::Sorted => Selection::AsGiven,
+        selection => selection,
+    };
+    if let Some(names) = field_names {
+        return Ok(Extract::Columns(
+            parse_columns(names, true)?,
+            column_selection,
         ));
+    }
+    let parse_list =
+        |list: &str| -> MyResult<PositionList> { Ok(arrange(parse_pos(list)?, selection)) };
+
+    let extract = match (fields, bytes, chars) {
+        // Names can only be looked up in the header of CSV files.
+        (Some(fields), None, None) if matches.get_flag("csv") && parse_pos(fields).is_err() => {
+            Extract::Columns(parse_columns(fields, false)?, column_selection)
+        }
+        (Some(fields), None, None) => Extract::Fields(parse_list(fields)?),
+        (None, Some(bytes), None) => Extract::Bytes(parse_list(bytes)?),
+        (None, None, Some(chars)) => Extract::Chars(parse_list(chars)?),
+        _ => {
+            return Err(From::from(
+                "Only one option of --fields, --bytes, or --chars is accepted",
+            ))
+        }
     };
 
     Ok(extract)
@@ -113,6 +212,9 @@ fn parse_fields_bytes_or_chars(matches: &ArgMatches) -> MyResult<Extract> {
 
 #[inline]
 fn parse_delimiter(matches: &ArgMatches) -> MyResult<u8> {
+    if matches.get_flag("csv") && matches.value_source("delim") == Some(ValueSource::DefaultValue) {
+        return Ok(b',');
+    }
     let delimiter = matches.get_one::<String>("delim").unwrap();
     if delimiter.len() != 1 {
         return Err(From::from(format!(
@@ -123,8 +225,8 @@ fn parse_delimiter(matches: &ArgMatches) -> MyResult<u8> {
 }
 
 pub fn run(config: Config) -> MyResult<()> {
-    for filename in config.files {
-        match open(&filename) {
+    for filename in &config.files {
+        match open(filename) {
             Err(e) => eprintln!("{filename}: {e}"),
             Ok(f) => match &config.extract {
                 Extract::Chars(char_pos) => {
@@ -137,19 +239,25 @@ pub fn run(config: Config) -> MyResult<()> {
                         println!("{}", extract_bytes(&line?, byte_pos));
                     }
                 }
+                Extract::Fields(_) if config.csv => cut_csv(f, filename, &config)?,
+                Extract::Columns(..) => cut_csv(f, filename, &config)?,
                 Extract::Fields(field_pos) => {
-                    let mut reader = ReaderBuilder::new()
-                        .delimiter(config.delimiter)
-                        .from_reader(f);
-
-                    let mut writer = WriterBuilder::new()
-                        .delimiter(config.delimiter)
-                        .from_writer(io::stdout());
-
-                    writer.write_record(extract_fields(reader.headers()?, field_pos))?;
-                    for record in reader.records() {
-                        let record = record?;
-                        writer.write_record(extract_fields(&record, field_pos))?;
+                    let delimiter = config.delimiter as char;
+                    for line in f.lines() {
+                        let line = line?;
+                        // Lines without a delimiter have no fields and are printed as they
+                        // are, like in POSIX cut.
+                        if !line.contains(delimiter) {
+                            if !config.only_delimited {
+                                println!("{line}");
+                            }
+                            continue;
+                        }
+                        let fields: Vec<_> = line.split(delimiter).collect();
+                        println!(
+                            "{}",
+                            extract_fields(&fields, field_pos).join(&config.output_delimiter)
+                        );
                     }
                 }
             },
@@ -157,3 +265,44 @@ pub fn run(config: Config) -> MyResult<()> {
     }
     Ok(())
 }
+
+/// Prints the selected fields of the CSV records in `file`. Records may have any number of
+/// fields. The header is a record like any other, unless the fields are selected by name.
+fn cut_csv(file: Box<dyn BufRead>, filename: &str, config: &Config) -> MyResult<()> {
+    let by_name = matches!(config.extract, Extract::Columns(..));
+    let mut reader = ReaderBuilder::new()
+        .delimiter(config.delimiter)
+        .has_headers(by_name)
+        .flexible(true)
+        .from_reader(file);
+
+    let mut writer = WriterBuilder::new()
+        .delimiter(config.output_delimiter.as_bytes()[0])
+        .flexible(true)
+        .from_writer(io::stdout());
+
+    let field_pos = match &config.extract {
+        Extract::Fields(field_pos) => field_pos.clone(),
+        Extract::Columns(columns, selection) => {
+            let headers = reader.headers()?;
+            let field_pos =
+                resolve_columns(columns, headers).map_err(|e| format!("{filename}: {e}"))?;
+            let field_pos = arrange(field_pos, *selection);
+            let fields: Vec<_> = headers.iter().collect();
+            writer.write_record(extract_fields(&fields, &field_pos))?;
+            field_pos
+        }
+        _ => unreachable!("Invalid CSV extraction"),
+    };
+
+    for record in reader.records() {
+        let record = record?;
+        if config.only_delimited && record.len() < 2 {
+            continue;
+        }
+        let fields: Vec<_> = record.iter().collect();
+        writer.write_record(extract_fields(&fields, &field_pos))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
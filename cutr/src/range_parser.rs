@@ -23,23 +23,31 @@ fn parse_range(range: &str) -> MyResult<ParsePos> {
         Ok(ParsePos::Digit(res))
     };
 
+    let digit = |n: &str| {
+        let ParsePos::Digit(num) = parse_number(n)? else {
+            unreachable!("Unreachable path")
+        };
+        Ok::<_, Box<dyn std::error::Error>>(num)
+    };
+
     match parts.len() {
         0 => Ok(parse_number(range)?),
         1 => parse_number(parts[0]),
-        2 => {
-            let ParsePos::Digit(num1) = parse_number(parts[0])? else {
-                unreachable!("Unreachable path")
-            };
-            let ParsePos::Digit(num2) = parse_number(parts[1])? else {
-                unreachable!("Unreachable path")
-            };
-            if num1 >= num2 {
-                return Err(From::from(format!(
-                    "First number in range ({num1}) must be lower than second number ({num2})"
-                )));
+        2 => match (parts[0], parts[1]) {
+            // "N-" runs to the end of the line, and "-M" starts at its beginning.
+            ("", "") => parse_number(""),
+            ("", end) => Ok(ParsePos::Range(0..digit(end)?)),
+            (start, "") => Ok(ParsePos::Range(digit(start)? - 1..usize::MAX)),
+            (start, end) => {
+                let (num1, num2) = (digit(start)?, digit(end)?);
+                if num1 >= num2 {
+                    return Err(From::from(format!(
+                        "First number in range ({num1}) must be lower than second number ({num2})"
+                    )));
+                }
+                Ok(ParsePos::Range(num1 - 1..num2))
             }
-            Ok(ParsePos::Range(num1 - 1..num2))
-        }
+        },
         _ => Err(From::from(
             "A range should be specified using only one '-'.",
         )),
@@ -62,9 +70,58 @@ pub(super) fn parse_pos(range: &str) -> MyResult<PositionList> {
         .collect()
 }
 
+/// How the positions of a list are selected.
+#[derive(Clone, Copy, Debug)]
+pub(super) enum Selection {
+    /// Each position once, in the order of the line.
+    Sorted,
+    /// The ranges as given, with --preserve-order.
+    AsGiven,
+    /// The positions not in the list, with --complement.
+    Complement,
+}
+
+pub(super) fn arrange(list: PositionList, selection: Selection) -> PositionList {
+    match selection {
+        Selection::Sorted => normalize(list),
+        Selection::AsGiven => list,
+        Selection::Complement => complement(list),
+    }
+}
+
+/// Sorts the ranges and merges the ones that overlap or touch, so that each position is
+/// selected once and in the order of the line, like GNU cut.
+pub(super) fn normalize(mut list: PositionList) -> PositionList {
+    list.sort_by_key(|r| r.start);
+    let mut merged: PositionList = Vec::with_capacity(list.len());
+    for range in list {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// Returns the positions not selected by `list`, in order.
+pub(super) fn complement(list: PositionList) -> PositionList {
+    let mut gaps = Vec::new();
+    let mut start = 0;
+    for range in normalize(list) {
+        if range.start > start {
+            gaps.push(start..range.start);
+        }
+        start = range.end;
+    }
+    if start < usize::MAX {
+        gaps.push(start..usize::MAX);
+    }
+    gaps
+}
+
 #[cfg(test)]
 mod unit_tests {
-    use super::parse_pos;
+    use super::{complement, normalize, parse_pos};
 
     #[test]
     fn test_parse_pos() {
@@ -120,9 +177,6 @@ mod unit_tests {
         let res = parse_pos("1,");
         assert!(res.is_err());
 
-        let res = parse_pos("1-");
-        assert!(res.is_err());
-
         let res = parse_pos("1-1-1");
         assert!(res.is_err());
 
@@ -172,5 +226,38 @@ mod unit_tests {
         let res = parse_pos("15,19-20");
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), vec![14..15, 18..20]);
+
+        // Open-ended ranges
+        let res = parse_pos("1-");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![0..usize::MAX]);
+
+        let res = parse_pos("3-,-2");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![2..usize::MAX, 0..2]);
+
+        let res = parse_pos("-0");
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), "illegal list value: \"0\"");
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert!(normalize(vec![]).is_empty());
+        assert_eq!(normalize(vec![0..1, 0..1]), vec![0..1]);
+        assert_eq!(normalize(vec![6..7, 0..1, 2..5]), vec![0..1, 2..5, 6..7]);
+        assert_eq!(normalize(vec![2..5, 0..2, 4..9]), vec![0..9]);
+        assert_eq!(
+            normalize(vec![3..usize::MAX, 0..2, 5..6]),
+            vec![0..2, 3..usize::MAX]
+        );
+    }
+
+    #[test]
+    fn test_complement() {
+        assert_eq!(complement(vec![]), vec![0..usize::MAX]);
+        assert_eq!(complement(vec![1..2]), vec![0..1, 2..usize::MAX]);
+        assert_eq!(complement(vec![4..6, 0..2]), vec![2..4, 6..usize::MAX]);
+        assert_eq!(complement(vec![2..usize::MAX]), vec![0..2]);
     }
 }
@@ -9,6 +9,10 @@ const CSV: &str = "tests/inputs/movies1.csv";
 const TSV: &str = "tests/inputs/movies1.tsv";
 
 const BOOKS: &str = "tests/inputs/books.tsv";
+const BOOKS_CSV: &str = "tests/inputs/books.csv";
+const MOVIES2_CSV: &str = "tests/inputs/movies2.csv";
+const RAGGED: &str = "tests/inputs/ragged.tsv";
+const USERS: &str = "tests/inputs/users.csv";
 
 #[test]
 fn skips_bad_file() -> TestResult {
@@ -31,6 +35,15 @@ fn skips_bad_file() -> TestResult {
 #[case(&[CSV, "-f", "1", "-b", "1"], "")]
 #[case(&[CSV, "-c", "1", "-f", "1"], "")]
 #[case(&[CSV, "-c", "1", "-b", "1"], "")]
+#[case(&[CSV, "-c", "1", "--complement", "--preserve-order"], "cannot be used with")]
+#[case(&[CSV, "-c", "1", "-s"], "only apply to --fields")]
+#[case(&[CSV, "-b", "1", "--csv"], "only apply to --fields")]
+#[case(&[CSV, "-f", "1", "--csv", "--output-delimiter", "::"], "--output-delimiter \"::\" must be a single byte with --csv")]
+#[case(&[USERS, "-F", "name"], "required arguments were not provided")]
+#[case(&[USERS, "--csv", "-F", "name", "-f", "1"], "cannot be used with")]
+#[case(&[USERS, "--csv", "-F", "name,phone"], "tests/inputs/users.csv: no column named \"phone\"")]
+#[case(&[USERS, "--csv", "-f", "1,created"], "no column named \"created\"")]
+#[case(&[USERS, "-f", "1,created"], "illegal list value: \"created\"")]
 fn dies(#[case] args: &[&str], #[case] expected: &str) -> TestResult {
     Command::cargo_bin(PRG)?
         .args(args)
@@ -76,7 +89,35 @@ fn dies_bad_string(#[case] args: &[&str]) -> TestResult {
 #[case(&[TSV, "-c", "1-2"], "tests/expected/movies1.tsv.c1-2.out")]
 #[case(&[TSV, "-c", "2-3"], "tests/expected/movies1.tsv.c2-3.out")]
 #[case(&[BOOKS, "-c", "1,1"], "tests/expected/books.c1,1.out")]
+#[case(&[BOOKS, "-c", "1,1", "--preserve-order"], "tests/expected/books.c1,1.preserve.out")]
 #[case(&[TSV, "-c", "1-8"], "tests/expected/movies1.tsv.c1-8.out")]
+#[case(&[TSV, "-f", "2-"], "tests/expected/movies1.tsv.f2-.out")]
+#[case(&[TSV, "--complement", "-f", "2-"], "tests/expected/movies1.tsv.f2-.complement.out")]
+#[case(&[TSV, "-c", "2-"], "tests/expected/movies1.tsv.c2-.out")]
+#[case(&[TSV, "--complement", "-c", "2-"], "tests/expected/movies1.tsv.c2-.complement.out")]
+#[case(&[TSV, "-f", "-2"], "tests/expected/movies1.tsv.f-2.out")]
+#[case(&[TSV, "--complement", "-f", "-2"], "tests/expected/movies1.tsv.f-2.complement.out")]
+#[case(&[TSV, "-c", "-2"], "tests/expected/movies1.tsv.c-2.out")]
+#[case(&[TSV, "--complement", "-c", "-2"], "tests/expected/movies1.tsv.c-2.complement.out")]
+#[case(&[TSV, "-f", "2,1"], "tests/expected/movies1.tsv.f2,1.out")]
+#[case(&[TSV, "--complement", "-f", "2,1"], "tests/expected/movies1.tsv.f2,1.complement.out")]
+#[case(&[TSV, "-c", "2,1"], "tests/expected/movies1.tsv.c2,1.out")]
+#[case(&[TSV, "--complement", "-c", "2,1"], "tests/expected/movies1.tsv.c2,1.complement.out")]
+#[case(&[TSV, "-f", "1,3-"], "tests/expected/movies1.tsv.f1,3-.out")]
+#[case(&[TSV, "--complement", "-f", "1,3-"], "tests/expected/movies1.tsv.f1,3-.complement.out")]
+#[case(&[TSV, "-c", "1,3-"], "tests/expected/movies1.tsv.c1,3-.out")]
+#[case(&[TSV, "--complement", "-c", "1,3-"], "tests/expected/movies1.tsv.c1,3-.complement.out")]
+#[case(&[RAGGED, "-f", "2-"], "tests/expected/ragged.tsv.f2-.out")]
+#[case(&[RAGGED, "-s", "-f", "2-"], "tests/expected/ragged.tsv.f2-.s.out")]
+#[case(&[RAGGED, "-f", "1,3", "--output-delimiter", " | "], "tests/expected/ragged.tsv.f1,3.odelim.out")]
+#[case(&[BOOKS_CSV, "-d", ",", "-f", "3"], "tests/expected/books.csv.f3.dcomma.out")]
+#[case(&[BOOKS_CSV, "--csv", "-d", ",", "-f", "3"], "tests/expected/books.csv.f3.csv.out")]
+#[case(&[MOVIES2_CSV, "--csv", "-d", ",", "-f", "1,3", "--output-delimiter=;"], "tests/expected/movies2.csv.f1,3.csv.odelim.out")]
+#[case(&[USERS, "--csv", "-F", "name,email"], "tests/expected/users.csv.Fname,email.out")]
+#[case(&[USERS, "--csv", "-F", "email,name"], "tests/expected/users.csv.Femail,name.out")]
+#[case(&[USERS, "--csv", "-F", "email,name", "--preserve-order"], "tests/expected/users.csv.Femail,name.preserve.out")]
+#[case(&[USERS, "--csv", "-f", "id,created_*"], "tests/expected/users.csv.fid,created_.out")]
+#[case(&[USERS, "--csv", "--complement", "-F", "created_*"], "tests/expected/users.csv.Fcreated_.complement.out")]
 fn run(#[case] args: &[&str], #[case] expected_file: &str) -> TestResult {
     println!("expected {expected_file}");
     let expected = fs::read_to_string(expected_file)?;
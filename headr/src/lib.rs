@@ -1,12 +1,29 @@
 use clap::{value_parser, Arg, Command};
 use command_utils::{open, MyResult};
-use std::io::{BufRead, Read};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::io::{self, BufRead, Read, Write};
+
+/// Describes which lines of each input are printed.
+#[derive(Debug, PartialEq)]
+enum LineSelection {
+    /// The first K lines.
+    First(usize),
+    /// The lines between START and END (both 1-based and inclusive).
+    Range(usize, usize),
+    /// Every Nth line.
+    Every(usize),
+    /// N lines chosen at random, optionally with a fixed seed.
+    Sample(usize, Option<u64>),
+}
 
 #[derive(Debug)]
 pub struct Config {
     files: Vec<String>,
-    lines: usize,
+    lines: LineSelection,
     bytes: Option<usize>,
+    quiet: bool,
+    verbose: bool,
+    zero_terminated: bool,
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -37,9 +54,69 @@ pub fn get_args() -> MyResult<Config> {
                 .help("Print the first K bytes of each file")
                 .num_args(1)
                 .required(false)
+                .conflicts_with_all(["count", "range", "every", "sample"])
+                .value_parser(value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("range")
+                .long("range")
+                .value_name("START:END")
+                .help("Print lines START to END (1-based, inclusive)")
+                .num_args(1)
+                .conflicts_with_all(["count", "every", "sample"])
+                .value_parser(parse_range),
+        )
+        .arg(
+            Arg::new("every")
+                .long("every")
+                .value_name("N")
+                .help("Print every Nth line")
+                .num_args(1)
+                .conflicts_with_all(["count", "sample"])
+                .value_parser(value_parser!(u64).range(1..)),
+        )
+        .arg(
+            Arg::new("sample")
+                .long("sample")
+                .value_name("N")
+                .help("Print N randomly chosen lines")
+                .num_args(1)
                 .conflicts_with("count")
                 .value_parser(value_parser!(usize)),
         )
+        .arg(
+            Arg::new("seed")
+                .long("seed")
+                .value_name("S")
+                .help("Random seed used by --sample")
+                .num_args(1)
+                .requires("sample")
+                .value_parser(value_parser!(u64)),
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .visible_alias("silent")
+                .help("Never print headers giving file names")
+                .num_args(0)
+                .overrides_with("verbose"),
+        )
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .help("Always print headers giving file names")
+                .num_args(0)
+                .overrides_with("quiet"),
+        )
+        .arg(
+            Arg::new("zero_terminated")
+                .short('z')
+                .long("zero-terminated")
+                .help("Line delimiter is NUL, not newline")
+                .num_args(0),
+        )
         .get_matches();
 
     Ok(Config {
@@ -48,48 +125,158 @@ pub fn get_args() -> MyResult<Config> {
             .unwrap()
             .map(|f| f.to_owned())
             .collect(),
-        lines: matches
-            .get_one::<usize>("count")
-            .map(|f| f.to_owned())
-            .unwrap_or(10),
+        lines: if let Some(&(start, end)) = matches.get_one::<(usize, usize)>("range") {
+            LineSelection::Range(start, end)
+        } else if let Some(&n) = matches.get_one::<u64>("every") {
+            LineSelection::Every(n as usize)
+        } else if let Some(&n) = matches.get_one::<usize>("sample") {
+            LineSelection::Sample(n, matches.get_one::<u64>("seed").copied())
+        } else {
+            LineSelection::First(
+                matches
+                    .get_one::<usize>("count")
+                    .map(|f| f.to_owned())
+                    .unwrap_or(10),
+            )
+        },
         bytes: matches.get_one::<usize>("bytes").map(|f| f.to_owned()),
+        quiet: matches.get_flag("quiet"),
+        verbose: matches.get_flag("verbose"),
+        zero_terminated: matches.get_flag("zero_terminated"),
     })
 }
 
 pub fn run(config: Config) -> MyResult<()> {
     let num_files = config.files.len();
+    let print_headers = config.verbose || (num_files > 1 && !config.quiet);
+    let delimiter = if config.zero_terminated { b'\0' } else { b'\n' };
+    let mut stdout = io::stdout().lock();
 
     for (file_num, filename) in config.files.iter().enumerate() {
         match open(filename) {
             Err(e) => eprintln!("{filename}: {e}"),
             Ok(mut f) => {
-                if num_files > 1 {
-                    println!(
-                        "{}===> {} <===",
+                if print_headers {
+                    writeln!(
+                        stdout,
+                        "{}==> {} <==",
                         if file_num > 0 { "\n" } else { "" },
                         filename
-                    )
+                    )?;
                 }
 
                 if let Some(num) = config.bytes {
                     let bytes: Result<Vec<_>, _> = f.bytes().take(num).collect();
-                    print!("{}", String::from_utf8_lossy(&bytes?));
+                    write!(stdout, "{}", String::from_utf8_lossy(&bytes?))?;
+                } else {
+                    print_lines(&mut f, &config.lines, delimiter, &mut stdout)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses a `START:END` pair of 1-based line numbers, where START must not exceed END.
+fn parse_range(val: &str) -> Result<(usize, usize), String> {
+    let err = || format!("expected START:END with 1 <= START <= END, got \"{val}\"");
+    let (start, end) = val.split_once(':').ok_or_else(err)?;
+    match (start.parse::<usize>(), end.parse::<usize>()) {
+        (Ok(start), Ok(end)) if start >= 1 && start <= end => Ok((start, end)),
+        _ => Err(err()),
+    }
+}
+
+fn print_lines(
+    f: &mut dyn BufRead,
+    selection: &LineSelection,
+    delimiter: u8,
+    out: &mut impl Write,
+) -> MyResult<()> {
+    let mut line = Vec::new();
+    let mut reservoir = Vec::new();
+    // Only created once --sample has filled the reservoir.
+    let mut rng: Option<StdRng> = None;
+
+    // Use BufRead::read_until so that the delimiter is not removed,
+    // Which will be useful in dealing with OS specific end line characters
+    // and with NUL terminated records.
+    for line_num in 1.. {
+        match selection {
+            LineSelection::First(n) if line_num > *n => break,
+            LineSelection::Range(_, end) if line_num > *end => break,
+            _ => {}
+        }
+
+        if f.read_until(delimiter, &mut line)? == 0 {
+            break;
+        }
+
+        match selection {
+            LineSelection::First(_) => out.write_all(&line)?,
+            LineSelection::Range(start, _) if line_num >= *start => out.write_all(&line)?,
+            LineSelection::Every(n) if line_num % n == 0 => out.write_all(&line)?,
+            // Reservoir sampling (Algorithm R): the first N lines fill the reservoir, after
+            // which the ith line replaces a random slot with probability N/i.
+            LineSelection::Sample(n, seed) => {
+                if reservoir.len() < *n {
+                    reservoir.push((line_num, std::mem::take(&mut line)));
                 } else {
-                    let mut line = String::new();
-                    // Use BufRead::read_line so that the newline character is not removed,
-                    // Which will be useful in dealing with OS specific end line characters.
-                    for _ in 0..config.lines {
-                        let bytes = f.read_line(&mut line)?;
-                        if bytes == 0 {
-                            break;
-                        }
-                        print!("{line}");
-                        line.clear();
+                    let rng = rng.get_or_insert_with(|| match seed {
+                        Some(seed) => StdRng::seed_from_u64(*seed),
+                        None => StdRng::from_entropy(),
+                    });
+                    let slot = rng.gen_range(0..line_num);
+                    if slot < *n {
+                        reservoir[slot] = (line_num, std::mem::take(&mut line));
                     }
                 }
             }
+            _ => {}
         }
+        line.clear();
+    }
+
+    // Print the sampled lines in the order they appeared in the input.
+    reservoir.sort_unstable_by_key(|(line_num, _)| *line_num);
+    for (_, line) in reservoir {
+        out.write_all(&line)?;
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::parse_range;
+
+    #[test]
+    fn test_parse_range() {
+        let res = parse_range("1:1");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), (1, 1));
+
+        let res = parse_range("3:10");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), (3, 10));
+
+        // Line numbers are 1-based
+        assert!(parse_range("0:10").is_err());
+
+        // START cannot be greater than END
+        assert!(parse_range("10:3").is_err());
+
+        // Both bounds are required
+        assert!(parse_range("3").is_err());
+        assert!(parse_range("3:").is_err());
+        assert!(parse_range(":3").is_err());
+
+        let res = parse_range("foo:bar");
+        assert!(res.is_err());
+        assert_eq!(
+            res.unwrap_err(),
+            "expected START:END with 1 <= START <= END, got \"foo:bar\""
+        );
+    }
+}
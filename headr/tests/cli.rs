@@ -17,9 +17,14 @@ const THREE: &str = "./tests/inputs/three.txt";
 
 const TEN: &str = "./tests/inputs/ten.txt";
 
+const ZERO: &str = "./tests/inputs/zero.txt";
+
 #[rstest]
 #[case(&["-c", &random_string(None), EMPTY], "invalid value '{}' for '--bytes <bytes>")]
 #[case(&["-n", &random_string(None), EMPTY], "invalid value '{}' for '--lines <count>")]
+#[case(&["--range", &random_string(None), EMPTY], "invalid value '{}' for '--range <START:END>")]
+#[case(&["--every", &random_string(None), EMPTY], "invalid value '{}' for '--every <N>")]
+#[case(&["--sample", &random_string(None), EMPTY], "invalid value '{}' for '--sample <N>")]
 fn dies_bad_arguments(#[case] args: &[&str], #[case] expected: &str) -> TestResult {
     let bad = args[1];
     let expected = expected.replace("{}", &bad);
@@ -44,6 +49,20 @@ fn dies_bytes_and_lines() -> TestResult {
     Ok(())
 }
 
+#[rstest]
+#[case(&["--range", "1:2", "-n", "1"], "the argument '--range <START:END>' cannot be used with '--lines <count>'")]
+#[case(&["--every", "2", "--sample", "1"], "the argument '--every <N>' cannot be used with '--sample <N>'")]
+#[case(&["--seed", "1"], "the following required arguments were not provided")]
+fn dies_conflicting_selections(#[case] args: &[&str], #[case] expected: &str) -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(expected));
+
+    Ok(())
+}
+
 #[test]
 fn skips_bad_file() -> TestResult {
     let bad = gen_bad_file();
@@ -83,6 +102,20 @@ fn skips_bad_file() -> TestResult {
 #[case(&[TEN, "-n", "4"], "tests/expected/ten.txt.n4.out")]
 #[case(&[TEN, "-c", "2"], "tests/expected/ten.txt.c2.out")]
 #[case(&[TEN, "-c", "4"], "tests/expected/ten.txt.c4.out")]
+#[case(&[ONE, "-v"], "tests/expected/one.txt.v.out")]
+#[case(&[ONE, "-q", "-v"], "tests/expected/one.txt.v.out")]
+#[case(&[ZERO, "-z"], "tests/expected/zero.txt.z.out")]
+#[case(&[ZERO, "-z", "-n", "2"], "tests/expected/zero.txt.z.n2.out")]
+#[case(&[ZERO, "-n", "1"], "tests/expected/zero.txt.n1.out")]
+#[case(&[TEN, "--range", "3:5"], "tests/expected/ten.txt.range3-5.out")]
+#[case(&[TEN, "--range", "9:20"], "tests/expected/ten.txt.range9-20.out")]
+#[case(&[TEN, "--every", "3"], "tests/expected/ten.txt.every3.out")]
+#[case(&[TEN, "--sample", "20"], "tests/expected/ten.txt.out")]
+#[case(&[EMPTY, ONE, TWO, THREE, TEN], "tests/expected/all.out")]
+#[case(&[EMPTY, ONE, TWO, THREE, TEN, "-n", "2"], "tests/expected/all.n2.out")]
+#[case(&[EMPTY, ONE, TWO, THREE, TEN, "-c", "2"], "tests/expected/all.c2.out")]
+#[case(&[EMPTY, ONE, TWO, THREE, TEN, "-q"], "tests/expected/all.q.out")]
+#[case(&[EMPTY, ONE, TWO, THREE, TEN, "-v", "-q", "-n", "2"], "tests/expected/all.n2.q.out")]
 fn run(#[case] args: &[&str], #[case] expected_file: &str) -> TestResult {
     // Extra work here due to lossy UTF
     let mut file = File::open(expected_file)?;
@@ -99,6 +132,30 @@ fn run(#[case] args: &[&str], #[case] expected_file: &str) -> TestResult {
     Ok(())
 }
 
+#[rstest]
+#[case(&[TEN, "--sample", "3"])]
+#[case(&[TEN, "--sample", "3", "--seed", "1"])]
+#[case(&[TEN, "--sample", "9", "--seed", "7"])]
+fn run_sample(#[case] args: &[&str]) -> TestResult {
+    let n: usize = args[2].parse()?;
+    let input = fs::read_to_string(TEN)?;
+    let input: Vec<_> = input.lines().collect();
+    let output = Command::cargo_bin(PRG)?.args(args).output()?;
+    assert!(output.status.success());
+
+    // Exactly n lines of the input, in the order they appear in it.
+    let stdout = String::from_utf8(output.stdout)?;
+    let positions: Vec<_> = stdout
+        .lines()
+        .map(|line| input.iter().position(|l| *l == line))
+        .collect();
+    assert_eq!(positions.len(), n);
+    assert!(positions.iter().all(Option::is_some));
+    assert!(positions.windows(2).all(|w| w[0] < w[1]));
+
+    Ok(())
+}
+
 #[rstest]
 #[case(&[], ONE, "tests/expected/one.txt.out")]
 #[case(&["-n", "2"], ONE, "tests/expected/one.txt.n2.out")]
@@ -0,0 +1,386 @@
+use command_utils::MyResult;
+use std::cmp::Reverse;
+use std::collections::hash_map::{DefaultHasher, Entry as MapEntry};
+use std::collections::{BinaryHeap, HashMap};
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufReader, BufWriter, Read, Seek, Write};
+
+/// The number of files the distinct lines are spread over once they don't fit in memory.
+/// Each key goes to a single partition, so a partition can be counted on its own.
+const PARTITIONS: usize = 64;
+
+/// A rough estimate of the memory used for each distinct line besides its text.
+const ENTRY_OVERHEAD: usize = 64;
+
+/// A distinct line of the input.
+#[derive(Debug, PartialEq)]
+pub struct Entry {
+    /// The position of the first line with this key.
+    pub seq: u64,
+    pub count: u64,
+    pub key: Vec<u8>,
+    /// The first line with this key, which is the one printed.
+    pub line: Vec<u8>,
+}
+
+/// Counts the distinct lines of the whole input, remembering the order they were first seen
+/// in. When the lines held in memory exceed `max_memory` bytes, they are written to
+/// temporary files partitioned by key and merged back by [`Counter::finish`].
+pub struct Counter {
+    index: HashMap<Vec<u8>, usize>,
+    entries: Vec<Entry>,
+    memory: usize,
+    max_memory: Option<usize>,
+    partitions: Option<Vec<BufWriter<File>>>,
+    next_seq: u64,
+}
+
+impl Counter {
+    pub fn new(max_memory: Option<usize>) -> Counter {
+        Counter {
+            index: HashMap::new(),
+            entries: Vec::new(),
+            memory: 0,
+            max_memory,
+            partitions: None,
+            next_seq: 0,
+        }
+    }
+
+    /// Counts `line`, which is compared with the other lines by `key`.
+    pub fn add(&mut self, key: Vec<u8>, line: &[u8]) -> MyResult<()> {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        match self.index.get(&key) {
+            Some(&i) => self.entries[i].count += 1,
+            None => {
+                // The key is stored both in the index and in the entry.
+                self.memory += 2 * key.len() + line.len() + ENTRY_OVERHEAD;
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push(Entry {
+                    seq,
+                    count: 1,
+                    key,
+                    line: line.to_owned(),
+                });
+                if self.max_memory.is_some_and(|max| self.memory > max) {
+                    self.spill()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves the entries held in memory to the partition files.
+    fn spill(&mut self) -> MyResult<()> {
+        let partitions = match &mut self.partitions {
+            Some(partitions) => partitions,
+            None => self.partitions.insert(
+                (0..PARTITIONS)
+                    .map(|_| tempfile::tempfile().map(BufWriter::new))
+                    .collect::<io::Result<_>>()?,
+            ),
+        };
+        for entry in self.entries.drain(..) {
+            let mut hasher = DefaultHasher::new();
+            entry.key.hash(&mut hasher);
+            write_entry(
+                &mut partitions[hasher.finish() as usize % PARTITIONS],
+                &entry,
+            )?;
+        }
+        self.index.clear();
+        self.memory = 0;
+        Ok(())
+    }
+
+    /// Returns the distinct lines in the order they were first seen.
+    pub fn finish(mut self) -> MyResult<Box<dyn Iterator<Item = MyResult<Entry>>>> {
+        if self.partitions.is_none() {
+            return Ok(Box::new(self.entries.into_iter().map(Ok)));
+        }
+
+        self.spill()?;
+        // A key may have been spilled more than once, so merge the entries of each partition
+        // and write them back in the order they were first seen.
+        let mut sorted = Vec::new();
+        for partition in self.partitions.take().unwrap() {
+            let mut file = partition.into_inner()?;
+            file.rewind()?;
+            let mut reader = BufReader::new(file);
+            let mut merged: HashMap<Vec<u8>, Entry> = HashMap::new();
+            while let Some(entry) = read_entry(&mut reader)? {
+                match merged.entry(entry.key.clone()) {
+                    MapEntry::Occupied(mut e) => {
+                        let e = e.get_mut();
+                        e.count += entry.count;
+                        if entry.seq < e.seq {
+                            e.seq = entry.seq;
+                            e.line = entry.line;
+                        }
+                    }
+                    MapEntry::Vacant(e) => {
+                        e.insert(entry);
+                    }
+                }
+            }
+
+            let mut entries: Vec<_> = merged.into_values().collect();
+            sorted.push(write_sorted(&mut entries, |e| e.seq)?);
+        }
+        Ok(Box::new(MergeSorted::new(sorted, |e| e.seq)?))
+    }
+}
+
+/// Returns the `n` entries with the highest counts, or all of them if `n` is `None`, from
+/// the most frequent down. Entries with the same count keep the order they were first seen
+/// in. With `n`, only `n` entries are held in memory at a time. Without it, the entries that
+/// take more than `max_memory` bytes are sorted in runs kept in temporary files.
+pub fn rank(
+    entries: impl Iterator<Item = MyResult<Entry>>,
+    n: Option<usize>,
+    max_memory: Option<usize>,
+) -> MyResult<Box<dyn Iterator<Item = MyResult<Entry>>>> {
+    let Some(n) = n else {
+        return sort_by_rank(entries, max_memory);
+    };
+
+    // The top of the heap is the entry that would be dropped first.
+    let mut heap = BinaryHeap::new();
+    for entry in entries {
+        let entry = entry?;
+        heap.push((Reverse(entry.count), entry.seq, Ranked(entry)));
+        if heap.len() > n {
+            heap.pop();
+        }
+    }
+    Ok(Box::new(
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|(_, _, Ranked(e))| Ok(e)),
+    ))
+}
+
+/// The order of [`rank`]: the most frequent first, then the first seen.
+fn rank_key(entry: &Entry) -> (Reverse<u64>, u64) {
+    (Reverse(entry.count), entry.seq)
+}
+
+/// Sorts all the entries by [`rank_key`]. Once the entries read take more than `max_memory`
+/// bytes, they are sorted and written to a temporary file, and the files are merged.
+fn sort_by_rank(
+    entries: impl Iterator<Item = MyResult<Entry>>,
+    max_memory: Option<usize>,
+) -> MyResult<Box<dyn Iterator<Item = MyResult<Entry>>>> {
+    let mut runs = Vec::new();
+    let mut run = Vec::new();
+    let mut memory = 0;
+    for entry in entries {
+        let entry = entry?;
+        memory += entry.key.len() + entry.line.len() + ENTRY_OVERHEAD;
+        run.push(entry);
+        if max_memory.is_some_and(|max| memory > max) {
+            runs.push(write_sorted(&mut run, rank_key)?);
+            memory = 0;
+        }
+    }
+
+    if runs.is_empty() {
+        run.sort_by_key(rank_key);
+        return Ok(Box::new(run.into_iter().map(Ok)));
+    }
+    if !run.is_empty() {
+        runs.push(write_sorted(&mut run, rank_key)?);
+    }
+    Ok(Box::new(MergeSorted::new(runs, rank_key)?))
+}
+
+/// Sorts `entries` by `key` and moves them to a temporary file, returned ready to be read.
+fn write_sorted<K: Ord>(
+    entries: &mut Vec<Entry>,
+    key: fn(&Entry) -> K,
+) -> MyResult<BufReader<File>> {
+    entries.sort_by_key(key);
+    let mut out = BufWriter::new(tempfile::tempfile()?);
+    for entry in entries.drain(..) {
+        write_entry(&mut out, &entry)?;
+    }
+    let mut file = out.into_inner()?;
+    file.rewind()?;
+    Ok(BufReader::new(file))
+}
+
+/// Wraps an entry in the rank heap, where it's never compared since the sequence numbers
+/// ahead of it are unique.
+struct Ranked(Entry);
+
+impl PartialEq for Ranked {
+    fn eq(&self, _: &Ranked) -> bool {
+        true
+    }
+}
+
+impl Eq for Ranked {}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Ranked) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Ranked {
+    fn cmp(&self, _: &Ranked) -> std::cmp::Ordering {
+        std::cmp::Ordering::Equal
+    }
+}
+
+/// Merges files of entries, each sorted by `key`, into a single sorted stream.
+struct MergeSorted<K> {
+    readers: Vec<BufReader<File>>,
+    heads: Vec<Option<Entry>>,
+    heap: BinaryHeap<Reverse<(K, usize)>>,
+    key: fn(&Entry) -> K,
+}
+
+impl<K: Ord> MergeSorted<K> {
+    fn new(mut readers: Vec<BufReader<File>>, key: fn(&Entry) -> K) -> MyResult<MergeSorted<K>> {
+        let mut heads = Vec::new();
+        let mut heap = BinaryHeap::new();
+        for (i, reader) in readers.iter_mut().enumerate() {
+            let head = read_entry(reader)?;
+            if let Some(entry) = &head {
+                heap.push(Reverse((key(entry), i)));
+            }
+            heads.push(head);
+        }
+        Ok(MergeSorted {
+            readers,
+            heads,
+            heap,
+            key,
+        })
+    }
+}
+
+impl<K: Ord> Iterator for MergeSorted<K> {
+    type Item = MyResult<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((_, i)) = self.heap.pop()?;
+        let entry = self.heads[i].take()?;
+        match read_entry(&mut self.readers[i]) {
+            Ok(Some(next)) => {
+                self.heap.push(Reverse(((self.key)(&next), i)));
+                self.heads[i] = Some(next);
+            }
+            Ok(None) => {}
+            Err(e) => return Some(Err(e)),
+        }
+        Some(Ok(entry))
+    }
+}
+
+fn write_entry(out: &mut impl Write, entry: &Entry) -> MyResult<()> {
+    out.write_all(&entry.seq.to_le_bytes())?;
+    out.write_all(&entry.count.to_le_bytes())?;
+    for text in [&entry.key, &entry.line] {
+        out.write_all(&(text.len() as u64).to_le_bytes())?;
+        out.write_all(text)?;
+    }
+    Ok(())
+}
+
+/// Reads an entry written by [`write_entry`], or `None` at the end of the file.
+fn read_entry(reader: &mut impl Read) -> MyResult<Option<Entry>> {
+    let mut seq = [0; 8];
+    match reader.read_exact(&mut seq) {
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        res => res?,
+    }
+    let read_u64 = |reader: &mut dyn Read| -> io::Result<u64> {
+        let mut buf = [0; 8];
+        reader.read_exact(&mut buf)?;
+        Ok(u64::from_le_bytes(buf))
+    };
+    let count = read_u64(reader)?;
+    let mut texts = [Vec::new(), Vec::new()];
+    for text in &mut texts {
+        let len = read_u64(reader)?;
+        reader.take(len).read_to_end(text)?;
+    }
+    let [key, line] = texts;
+    Ok(Some(Entry {
+        seq: u64::from_le_bytes(seq),
+        count,
+        key,
+        line,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rank, Counter, Entry};
+
+    fn count(lines: &[&str], max_memory: Option<usize>) -> Vec<Entry> {
+        let mut counter = Counter::new(max_memory);
+        for line in lines {
+            assert!(counter
+                .add(line.to_lowercase().into_bytes(), line.as_bytes())
+                .is_ok());
+        }
+        let entries = counter.finish();
+        assert!(entries.is_ok());
+        entries.unwrap().map(|e| e.unwrap()).collect()
+    }
+
+    #[test]
+    fn test_counter() {
+        let lines: Vec<_> = (0..500)
+            .map(|i| format!("{}\n", ["b", "A", "c", "a", "d"][i * 7 % 5]))
+            .chain(["e\n".to_string()])
+            .collect();
+        let lines: Vec<_> = lines.iter().map(|l| l.as_str()).collect();
+        let in_memory = count(&lines, None);
+        let summary: Vec<_> = in_memory
+            .iter()
+            .map(|e| (std::str::from_utf8(&e.line).unwrap(), e.count))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("b\n", 100),
+                ("c\n", 100),
+                ("d\n", 100),
+                ("A\n", 200),
+                ("e\n", 1)
+            ]
+        );
+
+        // Spilling after every new line gives the same result.
+        assert_eq!(count(&lines, Some(1)), in_memory);
+    }
+
+    #[test]
+    fn test_rank() {
+        let lines = ["x\n", "y\n", "z\n", "y\n", "z\n", "w\n"];
+        let ranked = |n, max_memory| -> Vec<String> {
+            let entries = rank(count(&lines, None).into_iter().map(Ok), n, max_memory);
+            assert!(entries.is_ok());
+            entries
+                .unwrap()
+                .map(|e| String::from_utf8(e.unwrap().line).unwrap())
+                .collect()
+        };
+
+        // Ties keep the order the lines were first seen in.
+        assert_eq!(ranked(None, None), ["y\n", "z\n", "x\n", "w\n"]);
+        assert_eq!(ranked(Some(3), None), ["y\n", "z\n", "x\n"]);
+        assert_eq!(ranked(Some(0), None), Vec::<String>::new());
+
+        // Sorting in runs of one or a few entries gives the same order.
+        for max_memory in [1, 150] {
+            assert_eq!(ranked(None, Some(max_memory)), ranked(None, None));
+        }
+    }
+}
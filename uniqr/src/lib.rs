@@ -1,14 +1,61 @@
-use clap::{Arg, Command};
+mod global;
+mod normalize;
+
+use clap::{value_parser, Arg, Command};
 use command_utils::{open, MyResult};
+use global::Counter;
+use normalize::{Normalizer, Token};
+use std::borrow::Cow;
 use std::fs::File;
 use std::io;
-use std::io::{BufRead, Write};
+use std::io::{BufRead, BufWriter, Write};
+
+/// How the groups printed by --all-repeated are delimited.
+#[derive(Debug, Eq, PartialEq)]
+enum Delimit {
+    None,
+    /// An empty line before each group.
+    Prepend,
+    /// An empty line between groups.
+    Separate,
+}
+
+/// Where --group puts the empty lines delimiting groups.
+#[derive(Debug, Eq, PartialEq)]
+enum GroupMode {
+    Separate,
+    Prepend,
+    Append,
+    /// Before each group and after the last one.
+    Both,
+}
 
 #[derive(Debug)]
 pub struct Config {
     in_file: String,
     out_file: Option<String>,
     count: bool,
+    repeated: bool,
+    all_repeated: Option<Delimit>,
+    unique: bool,
+    ignore_case: bool,
+    group: Option<GroupMode>,
+    skip_fields: usize,
+    skip_chars: usize,
+    check_chars: Option<usize>,
+    global: bool,
+    max_memory: Option<usize>,
+    top: Option<usize>,
+    sort_by_count: bool,
+    delimiter: u8,
+    normalizer: Option<Normalizer>,
+}
+
+/// A run of adjacent matching lines. All of them are kept only when they are printed,
+/// otherwise just the first one.
+struct Group {
+    lines: Vec<Vec<u8>>,
+    count: u64,
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -39,65 +86,509 @@ pub fn get_args() -> MyResult<Config> {
                 .long("count")
                 .required(false),
         )
+        .arg(
+            Arg::new("repeated")
+                .num_args(0)
+                .help("Only print duplicate lines, one for each group")
+                .short('d')
+                .long("repeated"),
+        )
+        .arg(
+            Arg::new("all_repeated")
+                .value_name("METHOD")
+                .help("Print all duplicate lines. Groups can be delimited with an empty line: none, prepend or separate")
+                .short('D')
+                .long("all-repeated")
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("none")
+                .value_parser(["none", "prepend", "separate"])
+                .conflicts_with("count"),
+        )
+        .arg(
+            Arg::new("unique")
+                .num_args(0)
+                .help("Only print unique lines")
+                .short('u')
+                .long("unique"),
+        )
+        .arg(
+            Arg::new("ignore_case")
+                .num_args(0)
+                .help("Ignore differences in case when comparing lines")
+                .short('i')
+                .long("ignore-case"),
+        )
+        .arg(
+            Arg::new("group")
+                .value_name("METHOD")
+                .help("Show all lines, delimiting groups with an empty line: separate, prepend, append or both")
+                .long("group")
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("separate")
+                .value_parser(["separate", "prepend", "append", "both"])
+                .conflicts_with_all(["count", "repeated", "all_repeated", "unique"]),
+        )
+        .arg(
+            Arg::new("skip_fields")
+                .value_name("N")
+                .help("Avoid comparing the first N fields, which are separated by blanks")
+                .short('f')
+                .long("skip-fields")
+                .num_args(1)
+                .default_value("0")
+                .value_parser(value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("skip_chars")
+                .value_name("N")
+                .help("Avoid comparing the first N characters, after the skipped fields")
+                .short('s')
+                .long("skip-chars")
+                .num_args(1)
+                .default_value("0")
+                .value_parser(value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("check_chars")
+                .value_name("N")
+                .help("Compare no more than N characters in lines")
+                .short('w')
+                .long("check-chars")
+                .num_args(1)
+                .value_parser(value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("global")
+                .num_args(0)
+                .help("Count matching lines anywhere in the input rather than only adjacent ones, printing them in the order they were first seen")
+                .long("global")
+                .conflicts_with_all(["all_repeated", "group"]),
+        )
+        .arg(
+            Arg::new("max_memory")
+                .value_name("SIZE")
+                .help("Keep the distinct lines counted by --global, --top or --sort-by-count in temporary files once they take more than SIZE bytes of memory. SIZE may end in K, M or G")
+                .long("max-memory")
+                .num_args(1)
+                .value_parser(parse_size),
+        )
+        .arg(
+            Arg::new("top")
+                .value_name("N")
+                .help("Print the N most frequent lines with their counts, like sort | uniq -c | sort -rn | head -n N")
+                .long("top")
+                .num_args(1)
+                .value_parser(value_parser!(usize))
+                .conflicts_with_all(["all_repeated", "group"]),
+        )
+        .arg(
+            Arg::new("sort_by_count")
+                .num_args(0)
+                .help("Print all the distinct lines with their counts, from the most frequent down")
+                .long("sort-by-count")
+                .conflicts_with_all(["all_repeated", "group"]),
+        )
+        .arg(
+            Arg::new("zero_terminated")
+                .num_args(0)
+                .help("Line delimiter is NUL, not newline")
+                .short('z')
+                .long("zero-terminated"),
+        )
+        .arg(
+            Arg::new("normalize")
+                .value_name("TOKENS")
+                .help("Mask the numbers, hex numbers, UUIDs and IP addresses in lines before comparing them. Print the resulting template followed by the count of each group, unless -c, -D, --group, --top or --sort-by-count print the lines themselves. TOKENS is a comma-separated list of numbers, hex, uuid and ip, all of them by default")
+                .long("normalize")
+                .num_args(0..=1)
+                .require_equals(true)
+                .value_delimiter(',')
+                .default_missing_values(["numbers", "hex", "uuid", "ip"])
+                .value_parser(["numbers", "hex", "uuid", "ip"]),
+        )
         .get_matches();
 
+    let top = matches.get_one::<usize>("top").copied();
+    let sort_by_count = matches.get_flag("sort_by_count");
+
     Ok(Config {
         in_file: matches.get_one::<String>("in_file").unwrap().to_owned(),
         out_file: matches.get_one::<String>("out_file").map(|f| f.to_owned()),
         count: matches.get_flag("count"),
+        repeated: matches.get_flag("repeated"),
+        all_repeated: matches
+            .get_one::<String>("all_repeated")
+            .map(|m| match m.as_str() {
+                "none" => Delimit::None,
+                "prepend" => Delimit::Prepend,
+                "separate" => Delimit::Separate,
+                _ => unreachable!("Invalid delimit method"),
+            }),
+        unique: matches.get_flag("unique"),
+        ignore_case: matches.get_flag("ignore_case"),
+        group: matches
+            .get_one::<String>("group")
+            .map(|m| match m.as_str() {
+                "separate" => GroupMode::Separate,
+                "prepend" => GroupMode::Prepend,
+                "append" => GroupMode::Append,
+                "both" => GroupMode::Both,
+                _ => unreachable!("Invalid group method"),
+            }),
+        skip_fields: *matches.get_one::<usize>("skip_fields").unwrap(),
+        skip_chars: *matches.get_one::<usize>("skip_chars").unwrap(),
+        check_chars: matches.get_one::<usize>("check_chars").copied(),
+        global: matches.get_flag("global") || top.is_some() || sort_by_count,
+        max_memory: matches.get_one::<usize>("max_memory").copied(),
+        top,
+        sort_by_count,
+        delimiter: if matches.get_flag("zero_terminated") {
+            b'\0'
+        } else {
+            b'\n'
+        },
+        normalizer: matches.get_many::<String>("normalize").map(|tokens| {
+            let tokens: Vec<_> = tokens
+                .map(|t| match t.as_str() {
+                    "numbers" => Token::Numbers,
+                    "hex" => Token::Hex,
+                    "uuid" => Token::Uuid,
+                    "ip" => Token::Ip,
+                    _ => unreachable!("Invalid token"),
+                })
+                .collect();
+            Normalizer::new(&tokens)
+        }),
     })
 }
 
+/// Parses a size in bytes, optionally followed by a K, M or G binary suffix.
+fn parse_size(val: &str) -> Result<usize, String> {
+    let (digits, unit) = match val.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => val.split_at(i),
+        None => (val, ""),
+    };
+    let multiplier: usize = match unit {
+        "" => 1,
+        "K" | "k" => 1 << 10,
+        "M" | "m" => 1 << 20,
+        "G" | "g" => 1 << 30,
+        _ => return Err(format!("invalid size \"{val}\"")),
+    };
+    digits
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| format!("invalid size \"{val}\""))
+}
+
 pub fn run(config: Config) -> MyResult<()> {
-    dbg!(&config);
     match open(&config.in_file) {
         Err(e) => {
             eprintln!("{}: {e}", config.in_file);
             return Err(e);
         }
         Ok(mut f) => {
-            let mut prev_line = String::new();
-            let mut count: u64 = 0;
-            let mut line = String::new();
-            let mut out_file: Box<dyn Write> = if let Some(out_path) = config.out_file {
-                Box::new(File::create(out_path)?)
+            let mut line = Vec::new();
+            let mut out_file: Box<dyn Write> = if let Some(out_path) = &config.out_file {
+                Box::new(BufWriter::new(File::create(out_path)?))
             } else {
-                Box::new(io::stdout())
+                Box::new(BufWriter::new(io::stdout()))
             };
+            if config.global {
+                count_globally(f, &mut out_file, &config)?;
+                return Ok(out_file.flush()?);
+            }
+
+            let keep_all_lines = config.all_repeated.is_some() || config.group.is_some();
+            let only_repeated = config.repeated || config.all_repeated.is_some();
+            let mut printed_any = false;
+
+            // Decides whether a completed group is printed, and how.
+            let mut print = |group: &Group| -> MyResult<()> {
+                let repeated = group.count > 1;
+                if group.count == 0 || (only_repeated && !repeated) || (config.unique && repeated) {
+                    return Ok(());
+                }
+
+                let delimit_before = match (&config.all_repeated, &config.group) {
+                    (Some(Delimit::Prepend), _) => true,
+                    (Some(Delimit::Separate), _) => printed_any,
+                    (_, Some(GroupMode::Prepend | GroupMode::Both)) => true,
+                    (_, Some(GroupMode::Separate)) => printed_any,
+                    _ => false,
+                };
+                if delimit_before {
+                    out_file.write_all(&[config.delimiter])?;
+                }
+                printed_any = true;
 
-            let mut print = |count: u64, text: &str| -> MyResult<()> {
-                if count > 0 {
-                    write!(
+                if keep_all_lines {
+                    for text in &group.lines {
+                        write_record(&mut out_file, None, text)?;
+                    }
+                } else if config.normalizer.is_some() && !config.count {
+                    write_template(
                         &mut out_file,
-                        "{}{}",
-                        if config.count {
-                            format!("{:>7} ", count)
-                        } else {
-                            "".to_string()
-                        },
-                        text
+                        group.count,
+                        &normalize(&group.lines[0], &config),
+                        config.delimiter,
                     )?;
+                } else {
+                    let count = config.count.then_some(group.count);
+                    write_record(&mut out_file, count, &group.lines[0])?;
                 }
 
+                if config.group == Some(GroupMode::Append) {
+                    out_file.write_all(&[config.delimiter])?;
+                }
                 Ok(())
             };
 
-            loop {
-                let bytes = f.read_line(&mut line)?;
-                if bytes == 0 {
-                    break;
-                }
-                if line.trim_end() != prev_line.trim_end() {
-                    print(count, &prev_line)?;
-                    prev_line = line.clone();
-                    count = 0;
+            let mut group = Group {
+                lines: vec![Vec::new()],
+                count: 0,
+            };
+            while f.read_until(config.delimiter, &mut line)? > 0 {
+                if group.count == 0 || !lines_match(&group.lines[0], &line, &config) {
+                    print(&group)?;
+                    group.lines.clear();
+                    group.lines.push(line.clone());
+                    group.count = 0;
+                } else if keep_all_lines {
+                    group.lines.push(line.clone());
                 }
-                count += 1;
+                group.count += 1;
                 line.clear();
             }
 
-            print(count, &prev_line)?;
+            print(&group)?;
+            if config.group == Some(GroupMode::Both) && printed_any {
+                out_file.write_all(&[config.delimiter])?;
+            }
+            out_file.flush()?;
         }
     }
     Ok(())
 }
+
+/// Counts the lines matching anywhere in the input rather than adjacent ones, and prints one
+/// of each in the order they were first seen, or from the most frequent down.
+fn count_globally(mut f: impl BufRead, out_file: &mut dyn Write, config: &Config) -> MyResult<()> {
+    let mut counter = Counter::new(config.max_memory);
+    let mut line = Vec::new();
+    while f.read_until(config.delimiter, &mut line)? > 0 {
+        let normalized = normalize(&line, config);
+        let key = compare_key(&normalized, config);
+        let key = if config.ignore_case {
+            fold_case(key)
+        } else {
+            key.to_vec()
+        };
+        counter.add(key, &line)?;
+        line.clear();
+    }
+
+    let entries = counter.finish()?.filter(|e| match e {
+        Ok(e) => !((config.repeated && e.count == 1) || (config.unique && e.count > 1)),
+        Err(_) => true,
+    });
+    if config.sort_by_count || config.top.is_some() {
+        for e in global::rank(entries, config.top, config.max_memory)? {
+            let mut e = e?;
+            // The last line of the input may be printed before others here, so it needs a
+            // delimiter of its own.
+            if e.line.last() != Some(&config.delimiter) {
+                e.line.push(config.delimiter);
+            }
+            write_record(out_file, Some(e.count), &e.line)?;
+        }
+    } else {
+        for e in entries {
+            let e = e?;
+            if config.normalizer.is_some() && !config.count {
+                write_template(
+                    out_file,
+                    e.count,
+                    &normalize(&e.line, config),
+                    config.delimiter,
+                )?;
+            } else {
+                let count = config.count.then_some(e.count);
+                write_record(out_file, count, &e.line)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes `line`, preceded by `count` if given. The line is written as it was read, so the
+/// last line of the input keeps its missing delimiter.
+fn write_record(out_file: &mut dyn Write, count: Option<u64>, line: &[u8]) -> MyResult<()> {
+    if let Some(count) = count {
+        write!(out_file, "{count:>7} ")?;
+    }
+    out_file.write_all(line)?;
+    Ok(())
+}
+
+/// Writes a template made by --normalize, followed by the number of lines matching it, as
+/// in `connection from <IP> failed x 231`.
+fn write_template(
+    out_file: &mut dyn Write,
+    count: u64,
+    line: &[u8],
+    delimiter: u8,
+) -> MyResult<()> {
+    match line.strip_suffix(&[delimiter]) {
+        Some(text) => {
+            out_file.write_all(text)?;
+            write!(out_file, " x {count}")?;
+            out_file.write_all(&[delimiter])?;
+        }
+        None => {
+            out_file.write_all(line)?;
+            write!(out_file, " x {count}")?;
+        }
+    }
+    Ok(())
+}
+
+/// Compares the keys of two lines, masked by --normalize and ignoring case if requested.
+fn lines_match(a: &[u8], b: &[u8], config: &Config) -> bool {
+    let (a, b) = (normalize(a, config), normalize(b, config));
+    let (a, b) = (compare_key(&a, config), compare_key(&b, config));
+    if config.ignore_case {
+        fold_case(a) == fold_case(b)
+    } else {
+        a == b
+    }
+}
+
+/// Returns `line` with the tokens masked by --normalize, if requested.
+fn normalize<'a>(line: &'a [u8], config: &Config) -> Cow<'a, [u8]> {
+    match &config.normalizer {
+        Some(normalizer) => normalizer.apply(line),
+        None => Cow::Borrowed(line),
+    }
+}
+
+/// Returns `key` in lower case. Keys that aren't valid UTF-8 only have their ASCII letters
+/// folded.
+fn fold_case(key: &[u8]) -> Vec<u8> {
+    match std::str::from_utf8(key) {
+        Ok(key) => key.to_lowercase().into_bytes(),
+        Err(_) => key.to_ascii_lowercase(),
+    }
+}
+
+/// Returns the part of `line` that is compared with other lines: what's left after removing
+/// the delimiter and skipping the requested fields and characters, up to --check-chars
+/// characters. Like GNU uniq, a field is a run of blanks followed by non-blank characters.
+fn compare_key<'a>(line: &'a [u8], config: &Config) -> &'a [u8] {
+    let is_blank = |b: &u8| *b == b' ' || *b == b'\t';
+    let mut key = line.strip_suffix(&[config.delimiter]).unwrap_or(line);
+    for _ in 0..config.skip_fields {
+        let start = key.iter().position(|b| !is_blank(b)).unwrap_or(key.len());
+        key = &key[start..];
+        let end = key.iter().position(is_blank).unwrap_or(key.len());
+        key = &key[end..];
+    }
+
+    key = &key[char_offset(key, config.skip_chars)..];
+    if let Some(n) = config.check_chars {
+        key = &key[..char_offset(key, n)];
+    }
+    key
+}
+
+/// Returns the offset of the `n`th character of `bytes`, or its length if it's shorter.
+/// Characters are told apart by their first byte, so invalid UTF-8 is counted rather than
+/// rejected.
+fn char_offset(bytes: &[u8], n: usize) -> usize {
+    bytes
+        .iter()
+        .enumerate()
+        .filter(|(_, &b)| (b as i8) >= -0x40)
+        .nth(n)
+        .map_or(bytes.len(), |(i, _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compare_key, fold_case, parse_size, Config};
+
+    fn config(skip_fields: usize, skip_chars: usize, check_chars: Option<usize>) -> Config {
+        Config {
+            in_file: "-".to_string(),
+            out_file: None,
+            count: false,
+            repeated: false,
+            all_repeated: None,
+            unique: false,
+            ignore_case: false,
+            group: None,
+            skip_fields,
+            skip_chars,
+            check_chars,
+            global: false,
+            max_memory: None,
+            top: None,
+            sort_by_count: false,
+            delimiter: b'\n',
+            normalizer: None,
+        }
+    }
+
+    #[test]
+    fn test_compare_key() {
+        let key = |line: &str, config: Config| -> String {
+            String::from_utf8(compare_key(line.as_bytes(), &config).to_vec()).unwrap()
+        };
+        let line = "2024-01-01 12:00:00\tserver started \n";
+        // Only the newline is left out, so trailing blanks are compared.
+        assert_eq!(
+            key(line, config(0, 0, None)),
+            "2024-01-01 12:00:00\tserver started "
+        );
+        // Skipped fields leave the blanks in front of the next field.
+        assert_eq!(key(line, config(2, 0, None)), "\tserver started ");
+        assert_eq!(key(line, config(2, 1, None)), "server started ");
+        assert_eq!(key(line, config(2, 1, Some(6))), "server");
+        assert_eq!(key(line, config(0, 11, Some(5))), "12:00");
+        // Skipping past the end of the line leaves an empty key.
+        assert_eq!(key(line, config(9, 0, None)), "");
+        assert_eq!(key("héllo\n", config(0, 40, None)), "");
+        // Characters, not bytes, are skipped and checked.
+        assert_eq!(key("héllo wörld", config(0, 2, Some(3))), "llo");
+        assert_eq!(key("héllo wörld", config(1, 2, Some(3))), "örl");
+
+        // Invalid UTF-8 is kept, and only the configured delimiter is left out.
+        let mut zero = config(0, 1, None);
+        zero.delimiter = b'\0';
+        assert_eq!(compare_key(b"a\xff\xfeb\n\0", &zero), b"\xff\xfeb\n");
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("0"), Ok(0));
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("64K"), Ok(64 * 1024));
+        assert_eq!(parse_size("2g"), Ok(2 << 30));
+        assert!(parse_size("").is_err());
+        assert!(parse_size("M").is_err());
+        assert!(parse_size("10T").is_err());
+        assert!(parse_size("1.5M").is_err());
+    }
+
+    #[test]
+    fn test_fold_case() {
+        assert_eq!(
+            fold_case("ÉCOLE école".as_bytes()),
+            "école école".as_bytes()
+        );
+        assert_eq!(fold_case(b"ABC\xff"), b"abc\xff");
+    }
+}
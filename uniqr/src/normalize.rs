@@ -0,0 +1,165 @@
+use regex::bytes::{Captures, Regex};
+use std::borrow::Cow;
+use std::net::IpAddr;
+
+/// A kind of token masked by --normalize.
+#[derive(Debug, Eq, PartialEq)]
+pub enum Token {
+    Numbers,
+    Hex,
+    Uuid,
+    Ip,
+}
+
+/// Replaces the tokens that vary between otherwise identical log lines with placeholders,
+/// turning each line into a template such as `connection from <IP> failed`.
+#[derive(Debug)]
+pub struct Normalizer {
+    patterns: Vec<Pattern>,
+}
+
+/// Decides whether a match of a pattern is masked.
+type Accept = fn(&[u8]) -> bool;
+
+/// The pattern of a token, with its placeholder. Matches are only masked if `accept` agrees.
+#[derive(Debug)]
+struct Pattern {
+    regex: Regex,
+    placeholder: &'static [u8],
+    accept: Accept,
+}
+
+impl Pattern {
+    fn new(token: Token) -> Pattern {
+        let (regex, placeholder, accept): (_, &[u8], Accept) = match token {
+            Token::Uuid => (
+                r"\b[[:xdigit:]]{8}-[[:xdigit:]]{4}-[[:xdigit:]]{4}-[[:xdigit:]]{4}-[[:xdigit:]]{12}\b",
+                b"<UUID>",
+                |_| true,
+            ),
+            // Runs of hex digits, dots and colons, which are only masked if they parse as an
+            // IPv4 or IPv6 address. That leaves times like 10:00:01 alone.
+            Token::Ip => (
+                r"[[:xdigit:]]*[.:][[:xdigit:].:]*[[:xdigit:]]|::",
+                b"<IP>",
+                is_ip,
+            ),
+            // 0x-prefixed numbers, and long runs of hex digits with both a decimal digit and
+            // a letter, so that words like "deadbeef" are kept and long decimal numbers like
+            // timestamps are left to the number mask.
+            Token::Hex => (
+                r"\b(?:0[xX][[:xdigit:]]+|[[:xdigit:]]{8,})\b",
+                b"<HEX>",
+                |t| {
+                    t.starts_with(b"0x")
+                        || t.starts_with(b"0X")
+                        || (t.iter().any(u8::is_ascii_digit)
+                            && t.iter().any(u8::is_ascii_alphabetic))
+                },
+            ),
+            Token::Numbers => (r"[0-9]+(?:\.[0-9]+)?", b"<NUM>", |_| true),
+        };
+        Pattern {
+            regex: Regex::new(regex).unwrap(),
+            placeholder,
+            accept,
+        }
+    }
+}
+
+impl Normalizer {
+    pub fn new(tokens: &[Token]) -> Normalizer {
+        // UUIDs and addresses come first, as they're made of hex digits and numbers themselves.
+        let order = [Token::Uuid, Token::Ip, Token::Hex, Token::Numbers];
+        Normalizer {
+            patterns: order
+                .into_iter()
+                .filter(|token| tokens.contains(token))
+                .map(Pattern::new)
+                .collect(),
+        }
+    }
+
+    /// Returns `line` with the tokens masked.
+    pub fn apply<'a>(&self, line: &'a [u8]) -> Cow<'a, [u8]> {
+        let mut line = Cow::Borrowed(line);
+        for Pattern {
+            regex,
+            placeholder,
+            accept,
+        } in &self.patterns
+        {
+            let replaced = regex.replace_all(&line, |caps: &Captures| {
+                let m = caps.get(0).unwrap();
+                // Tokens following a letter, like the 8 in "utf8" or the 86 in "x86_64", are
+                // part of a word. Units after them, like in "12ms", are fine.
+                let glued = line[..m.start()]
+                    .last()
+                    .is_some_and(|b| b.is_ascii_alphabetic() || *b == b'_');
+                if accept(m.as_bytes()) && !glued {
+                    placeholder.to_vec()
+                } else {
+                    m.as_bytes().to_vec()
+                }
+            });
+            if let Cow::Owned(replaced) = replaced {
+                line = Cow::Owned(replaced);
+            }
+        }
+        line
+    }
+}
+
+fn is_ip(token: &[u8]) -> bool {
+    std::str::from_utf8(token).is_ok_and(|t| t.parse::<IpAddr>().is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Normalizer, Token};
+
+    fn normalize(tokens: &[Token], line: &str) -> String {
+        let normalizer = Normalizer::new(tokens);
+        String::from_utf8(normalizer.apply(line.as_bytes()).into_owned()).unwrap()
+    }
+
+    #[test]
+    fn test_normalize() {
+        let all = [Token::Numbers, Token::Hex, Token::Uuid, Token::Ip];
+        assert_eq!(
+            normalize(&all, "connection from 10.0.0.12 failed after 3.5s\n"),
+            "connection from <IP> failed after <NUM>s\n"
+        );
+        assert_eq!(
+            normalize(&all, "10:00:01 fe80::1 ::1 2001:db8:0:0:0:0:2:1 1.2.3"),
+            "<NUM>:<NUM>:<NUM> <IP> <IP> <IP> <NUM>.<NUM>"
+        );
+        assert_eq!(
+            normalize(
+                &all,
+                "job 123e4567-e89b-12d3-a456-426614174000 at 0x7ffd5e8 sha 9f86d081"
+            ),
+            "job <UUID> at <HEX> sha <HEX>"
+        );
+        // Words made of hex letters, and digits that are part of a word, are kept.
+        assert_eq!(
+            normalize(&all, "deadbeef accepted utf8 x86_64 id=42"),
+            "deadbeef accepted utf8 x86_64 id=<NUM>"
+        );
+        // Long decimal numbers, like epoch times, are numbers rather than hex.
+        assert_eq!(
+            normalize(&all, "login at 1700000000 from 12345678"),
+            "login at <NUM> from <NUM>"
+        );
+
+        // Only the requested tokens are masked.
+        assert_eq!(
+            normalize(&[Token::Ip], "10.0.0.1 port 8080"),
+            "<IP> port 8080"
+        );
+        assert_eq!(
+            normalize(&[Token::Numbers], "10.0.0.1 port 8080"),
+            "<NUM>.<NUM> port <NUM>"
+        );
+    }
+}
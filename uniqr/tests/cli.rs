@@ -79,6 +79,13 @@ const T6: Test = Test {
     out_count: "tests/expected/t6.txt.c.out",
 };
 
+const GROUPS: &str = "tests/inputs/groups.txt";
+const LOG: &str = "tests/inputs/log.txt";
+const UNSORTED: &str = "tests/inputs/unsorted.txt";
+const BYTES: &str = "tests/inputs/bytes.txt";
+const ZERO: &str = "tests/inputs/zero.txt";
+const NOISY: &str = "tests/inputs/noisy.txt";
+
 #[test]
 fn dies_bad_file() -> TestResult {
     let bad = gen_bad_file();
@@ -92,6 +99,23 @@ fn dies_bad_file() -> TestResult {
     Ok(())
 }
 
+#[rstest]
+#[case(&["-D", "-c"])]
+#[case(&["--group", "-c"])]
+#[case(&["--group", "-d"])]
+#[case(&["--group", "-u"])]
+#[case(&["--global", "-D"])]
+#[case(&["--top", "3", "--group"])]
+fn dies_conflicting_options(#[case] args: &[&str]) -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+
+    Ok(())
+}
+
 #[rstest]
 #[case(&[EMPTY.input], EMPTY.out)]
 #[case(&[EMPTY.input, "-c"], EMPTY.out_count)]
@@ -115,8 +139,45 @@ fn dies_bad_file() -> TestResult {
 #[case(&[T4.input, "-c"], T4.out_count)]
 #[case(&[T5.input, "-c"], T5.out_count)]
 #[case(&[T6.input, "-c"], T6.out_count)]
+#[case(&[GROUPS, "-d"], "tests/expected/groups.txt.d.out")]
+#[case(&[GROUPS, "--unique"], "tests/expected/groups.txt.u.out")]
+#[case(&[GROUPS, "-D"], "tests/expected/groups.txt.D.out")]
+#[case(&[GROUPS, "--all-repeated=prepend"], "tests/expected/groups.txt.D-prepend.out")]
+#[case(&[GROUPS, "--all-repeated=separate"], "tests/expected/groups.txt.D-separate.out")]
+#[case(&[GROUPS, "-i", "-c"], "tests/expected/groups.txt.ic.out")]
+#[case(&[GROUPS, "--ignore-case", "--repeated"], "tests/expected/groups.txt.id.out")]
+#[case(&[GROUPS, "-c", "-d"], "tests/expected/groups.txt.cd.out")]
+#[case(&[GROUPS, "--group"], "tests/expected/groups.txt.group.out")]
+#[case(&[GROUPS, "--group=prepend"], "tests/expected/groups.txt.group-prepend.out")]
+#[case(&[GROUPS, "--group=append"], "tests/expected/groups.txt.group-append.out")]
+#[case(&[GROUPS, "--group=both", "-i"], "tests/expected/groups.txt.group-both.i.out")]
+#[case(&[LOG, "-f", "2", "-c"], "tests/expected/log.txt.f2.c.out")]
+#[case(&[LOG, "--skip-fields=2", "-i", "-c"], "tests/expected/log.txt.f2.i.c.out")]
+#[case(&[LOG, "-s", "20"], "tests/expected/log.txt.s20.out")]
+#[case(&[LOG, "-f", "2", "--check-chars", "14", "-c"], "tests/expected/log.txt.f2.w14.c.out")]
+#[case(&[LOG, "-w", "10", "-c"], "tests/expected/log.txt.w10.c.out")]
+#[case(&[UNSORTED, "--global"], "tests/expected/unsorted.txt.global.out")]
+#[case(&[UNSORTED, "--global", "-c", "-i"], "tests/expected/unsorted.txt.global.c.i.out")]
+#[case(&[UNSORTED, "--global", "-d"], "tests/expected/unsorted.txt.global.d.out")]
+#[case(&[UNSORTED, "--top", "2"], "tests/expected/unsorted.txt.top2.out")]
+#[case(&[UNSORTED, "--sort-by-count"], "tests/expected/unsorted.txt.sort-by-count.out")]
+#[case(&[UNSORTED, "--global", "--max-memory", "1"], "tests/expected/unsorted.txt.global.out")]
+#[case(&[UNSORTED, "--sort-by-count", "--max-memory=1K"], "tests/expected/unsorted.txt.sort-by-count.out")]
+#[case(&[BYTES], "tests/expected/bytes.txt.out")]
+#[case(&[BYTES, "-c"], "tests/expected/bytes.txt.c.out")]
+// The last line keeps its missing newline.
+#[case(&[T4.input, "-u"], "tests/expected/t4.txt.u.out")]
+#[case(&[T4.input, "-c", "-u"], "tests/expected/t4.txt.cu.out")]
+#[case(&[ZERO, "-z", "-c"], "tests/expected/zero.txt.z.c.out")]
+#[case(&[ZERO, "--zero-terminated", "--group"], "tests/expected/zero.txt.z.group.out")]
+#[case(&[NOISY, "--normalize"], "tests/expected/noisy.txt.normalize.out")]
+#[case(&[NOISY, "--normalize=ip,uuid"], "tests/expected/noisy.txt.normalize-ip-uuid.out")]
+#[case(&[NOISY, "--normalize", "-c"], "tests/expected/noisy.txt.normalize.c.out")]
+#[case(&[NOISY, "--normalize", "--sort-by-count"], "tests/expected/noisy.txt.normalize.sort-by-count.out")]
+#[case(&[NOISY, "--normalize", "-D"], "tests/expected/noisy.txt.normalize.D.out")]
+#[case(&[NOISY, "--normalize", "--global"], "tests/expected/noisy.txt.normalize.global.out")]
 fn run(#[case] args: &[&str], #[case] expected_out: &str) -> TestResult {
-    let expected = fs::read_to_string(expected_out)?;
+    let expected = fs::read(expected_out)?;
     Command::cargo_bin(PRG)?
         .args(args)
         .assert()
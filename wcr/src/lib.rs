@@ -1,16 +1,60 @@
-use clap::{parser::ValueSource, Arg, Command};
+mod output;
+mod walk;
+
+use clap::{parser::ValueSource, value_parser, Arg, ArgAction, Command};
 use command_utils::{open, MyResult};
-use std::io::BufRead;
+use output::{num_digits, OutputFormat, Printer};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthChar;
+
+/// How words are told apart.
+#[derive(Debug, Eq, PartialEq)]
+enum WordMode {
+    /// Words are separated by whitespace, like GNU wc.
+    Whitespace,
+    /// Words are found with the Unicode word boundaries of UAX #29, so that e.g. Japanese
+    /// text isn't counted as a single word.
+    Unicode,
+}
+
+/// Controls when the line with the totals is printed.
+#[derive(Debug, Eq, PartialEq)]
+enum TotalMode {
+    /// Only when more than one file is counted.
+    Auto,
+    Always,
+    /// Print the totals without any of the per-file counts.
+    Only,
+    Never,
+}
 
 #[derive(Debug)]
 pub struct Config {
     files: Vec<String>,
     lines: bool,
     words: bool,
+    word_mode: WordMode,
     bytes: bool,
     chars: bool,
+    graphemes: bool,
+    max_line_length: bool,
+    total: TotalMode,
+    jobs: usize,
+    format: OutputFormat,
+    by_extension: bool,
 }
 
+/// Regular files at least this large are split into byte ranges that are counted in
+/// parallel, when a single file is counted with more than one job.
+const PARALLEL_SIZE_THRESHOLD: u64 = 64 * 1024 * 1024;
+
 pub fn get_args() -> MyResult<Config> {
     let matches = Command::new("wcr")
         .version("0.1.0")
@@ -53,162 +97,831 @@ pub fn get_args() -> MyResult<Config> {
         )
         .arg(
             Arg::new("words")
-                .value_name("WORDS")
-                .help("Show word count")
+                .value_name("MODE")
+                .help("Show word count, with words separated by whitespace or found by Unicode word boundaries")
                 .short('w')
                 .long("words")
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("whitespace")
+                .value_parser(["whitespace", "unicode"])
+                .required(false),
+        )
+        .arg(
+            Arg::new("graphemes")
+                .value_name("GRAPHEMES")
+                .help("Show the count of extended grapheme clusters")
+                .long("graphemes")
                 .num_args(0)
                 .required(false),
         )
+        .arg(
+            Arg::new("max_line_length")
+                .help("Show the maximum display width")
+                .short('L')
+                .long("max-line-length")
+                .num_args(0)
+                .required(false),
+        )
+        .arg(
+            Arg::new("files0_from")
+                .value_name("F")
+                .help("Read input from the files specified by NUL-terminated names in file F. If F is - then read names from standard input")
+                .long("files0-from")
+                .num_args(1)
+                .conflicts_with("input_files"),
+        )
+        .arg(
+            Arg::new("total")
+                .value_name("WHEN")
+                .help("When to print a line with total counts")
+                .long("total")
+                .num_args(1)
+                .default_value("auto")
+                .value_parser(["auto", "always", "only", "never"]),
+        )
+        .arg(
+            Arg::new("jobs")
+                .value_name("N")
+                .help("Count up to N files at a time, or split a single large file into N parts. 0 uses all the available CPUs")
+                .short('j')
+                .long("jobs")
+                .num_args(1)
+                .default_value("1")
+                .value_parser(value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("format")
+                .value_name("FORMAT")
+                .help("Output format. The json, csv and tsv formats print a record with the named counts and the path of each file, followed by a record without a path for the totals")
+                .long("format")
+                .num_args(1)
+                .default_value("table")
+                .value_parser(["table", "json", "csv", "tsv"]),
+        )
+        .arg(
+            Arg::new("recursive")
+                .help("Count the files in directories and their subdirectories, leaving out the ones ignored by .gitignore files")
+                .short('r')
+                .long("recursive")
+                .num_args(0),
+        )
+        .arg(
+            Arg::new("exclude")
+                .value_name("GLOB")
+                .help("Leave out the files and directories matching GLOB when counting recursively")
+                .long("exclude")
+                .num_args(1)
+                .action(ArgAction::Append)
+                .requires("recursive"),
+        )
+        .arg(
+            Arg::new("by_extension")
+                .help("Print the number of files and their counts for each file extension instead of each file")
+                .long("by-extension")
+                .num_args(0),
+        )
         .get_matches();
 
     // if any of the flags came from command line then others should be false. If none of the options
     // came from command line then make them true.
     let mut lines = matches.get_flag("lines");
-    let mut words = matches.get_flag("words");
+    let word_mode = matches.get_one::<String>("words");
+    let mut words = word_mode.is_some();
     let mut bytes = matches.get_flag("bytes");
     let chars = matches.get_flag("chars");
+    let graphemes = matches.get_flag("graphemes");
+    let max_line_length = matches.get_flag("max_line_length");
 
-    if ["lines", "words", "bytes", "chars"]
-        .iter()
-        .all(|v| matches.value_source(v) != Some(ValueSource::CommandLine))
+    if [
+        "lines",
+        "words",
+        "bytes",
+        "chars",
+        "graphemes",
+        "max_line_length",
+    ]
+    .iter()
+    .all(|v| matches.value_source(v) != Some(ValueSource::CommandLine))
     {
         lines = true;
         words = true;
         bytes = true;
     }
 
-    Ok(Config {
-        files: matches
+    let mut files = match matches.get_one::<String>("files0_from") {
+        Some(f) => read_files0(f)?,
+        None => matches
             .get_many::<String>("input_files")
             .unwrap()
             .map(|f| f.to_owned())
             .collect(),
+    };
+    if matches.get_flag("recursive") {
+        let excludes: Vec<String> = matches
+            .get_many::<String>("exclude")
+            .unwrap_or_default()
+            .map(|e| e.to_owned())
+            .collect();
+        files = walk::expand_dirs(&files, &excludes)?;
+    }
+
+    Ok(Config {
+        files,
         lines,
         words,
+        word_mode: match word_mode.map(|m| m.as_str()) {
+            Some("unicode") => WordMode::Unicode,
+            _ => WordMode::Whitespace,
+        },
         bytes,
         chars,
+        graphemes,
+        max_line_length,
+        total: match matches.get_one::<String>("total").unwrap().as_str() {
+            "auto" => TotalMode::Auto,
+            "always" => TotalMode::Always,
+            "only" => TotalMode::Only,
+            "never" => TotalMode::Never,
+            _ => unreachable!("Invalid total mode"),
+        },
+        jobs: match matches.get_one::<usize>("jobs").unwrap() {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            &n => n,
+        },
+        format: match matches.get_one::<String>("format").unwrap().as_str() {
+            "table" => OutputFormat::Table,
+            "json" => OutputFormat::Json,
+            "csv" => OutputFormat::Csv,
+            "tsv" => OutputFormat::Tsv,
+            _ => unreachable!("Invalid output format"),
+        },
+        by_extension: matches.get_flag("by_extension"),
     })
 }
 
+/// Reads the NUL-terminated file names listed in `filename`, or in standard input if
+/// `filename` is "-".
+fn read_files0(filename: &str) -> MyResult<Vec<String>> {
+    let mut file = open(filename)?;
+    let mut files = Vec::new();
+    let mut name = Vec::new();
+    while file.read_until(b'\0', &mut name)? > 0 {
+        if name.last() == Some(&b'\0') {
+            name.pop();
+        }
+        if name.is_empty() {
+            return Err(From::from(format!(
+                "{filename}: invalid zero-length file name"
+            )));
+        }
+        files.push(String::from_utf8_lossy(&name).into_owned());
+        name.clear();
+    }
+    Ok(files)
+}
+
 pub fn run(config: Config) -> MyResult<()> {
-    let mut total_lines: usize = 0;
-    let mut total_words: usize = 0;
-    let mut total_bytes: usize = 0;
-    let mut total_chars: usize = 0;
+    let mut total = FileInfo::default();
+    let mut num_files = 0;
+    // The number of files and their counts for each extension, with --by-extension.
+    let mut extensions: BTreeMap<String, (usize, FileInfo)> = BTreeMap::new();
+    let mut printer = Printer::new(&config, number_width(&config));
 
-    for filename in &config.files {
-        match open(filename) {
+    let mut report = |filename: &str, res: Result<FileInfo, String>| -> MyResult<()> {
+        match res {
             Err(e) => eprintln!("{filename}: {e}"),
-            Ok(f) => {
-                let res = count(f)?;
-                println!(
-                    "{}{}{}{}{}",
-                    format_field(res.num_lines, config.lines),
-                    format_field(res.num_words, config.words),
-                    format_field(res.num_bytes, config.bytes),
-                    format_field(res.num_chars, config.chars),
+            Ok(res) => {
+                if config.by_extension {
+                    let extension = Path::new(filename)
+                        .extension()
+                        .map(|e| e.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    let (files, info) = extensions.entry(extension).or_default();
+                    *files += 1;
+                    info.add(&res);
+                } else if config.total != TotalMode::Only {
+                    printer.print_file(&res, filename)?;
+                }
+                total.add(&res);
+                num_files += 1;
+            }
+        }
+        Ok(())
+    };
+
+    if config.jobs > 1 && config.files.len() > 1 {
+        // Workers take the next file from a shared index, and the results are printed in
+        // the order of the arguments as soon as all the files before them are done.
+        let next_file = AtomicUsize::new(0);
+        let (tx, rx) = mpsc::channel();
+        thread::scope(|s| -> MyResult<()> {
+            for _ in 0..config.jobs.min(config.files.len()) {
+                let tx = tx.clone();
+                let (config, next_file) = (&config, &next_file);
+                s.spawn(move || loop {
+                    let i = next_file.fetch_add(1, Ordering::Relaxed);
+                    let Some(filename) = config.files.get(i) else {
+                        break;
+                    };
                     if filename == "-" {
-                        "".to_string()
-                    } else {
-                        format!(" {filename}")
+                        continue;
+                    }
+                    let res = count_file(filename, config, false).map_err(|e| e.to_string());
+                    if tx.send((i, res)).is_err() {
+                        break;
                     }
-                );
-                total_lines += res.num_lines;
-                total_words += res.num_words;
-                total_bytes += res.num_bytes;
-                total_chars += res.num_chars;
+                });
             }
+            drop(tx);
+
+            let mut pending = HashMap::new();
+            let mut results = rx.into_iter();
+            for (i, filename) in config.files.iter().enumerate() {
+                let res = if filename == "-" {
+                    // Standard input is counted here, in the order of the arguments, as
+                    // workers reading it at the same time would each get a part of it.
+                    count_file(filename, &config, false).map_err(|e| e.to_string())
+                } else {
+                    loop {
+                        if let Some(res) = pending.remove(&i) {
+                            break res;
+                        }
+                        let Some((j, res)) = results.next() else {
+                            unreachable!("Worker stopped before counting {filename}");
+                        };
+                        pending.insert(j, res);
+                    }
+                };
+                report(filename, res)?;
+            }
+            Ok(())
+        })?;
+    } else {
+        let split = config.files.len() == 1;
+        for filename in &config.files {
+            report(
+                filename,
+                count_file(filename, &config, split).map_err(|e| e.to_string()),
+            )?;
         }
     }
 
-    if config.files.len() > 1 {
-        println!(
-            "{}{}{}{} total",
-            format_field(total_lines, config.lines),
-            format_field(total_words, config.words),
-            format_field(total_bytes, config.bytes),
-            format_field(total_chars, config.chars),
-        );
+    let num_rows = if config.by_extension {
+        if config.total != TotalMode::Only {
+            for (extension, (files, info)) in &extensions {
+                printer.print_extension(info, *files, extension)?;
+            }
+        }
+        extensions.len()
+    } else {
+        config.files.len()
+    };
+
+    let files = config.by_extension.then_some(num_files);
+    match config.total {
+        TotalMode::Auto if num_rows > 1 => printer.print_total(&total, files)?,
+        TotalMode::Always | TotalMode::Only => printer.print_total(&total, files)?,
+        _ => {}
     }
-    Ok(())
+    printer.finish()
 }
 
-fn format_field(value: usize, show: bool) -> String {
-    if show {
-        format!("{:>8}", value)
-    } else {
-        "".to_string()
+/// Computes the width of the table columns like GNU wc: wide enough for the total size of
+/// the regular files, and at least 7 when any input isn't a regular file, since its size is
+/// unknown. A single count of a single file isn't padded at all.
+fn number_width(config: &Config) -> usize {
+    let num_counts = [
+        config.lines,
+        config.words,
+        config.bytes,
+        config.chars,
+        config.graphemes,
+        config.max_line_length,
+    ]
+    .iter()
+    .filter(|&&c| c)
+    .count();
+    if config.files.len() == 1 && num_counts == 1 && !config.by_extension {
+        return 1;
+    }
+
+    let mut min_width = 1;
+    let mut regular_total = 0;
+    for filename in &config.files {
+        match regular_file_size(filename) {
+            Some(size) => regular_total += size as u64,
+            None if filename == "-" || fs::metadata(filename).is_ok() => min_width = 7,
+            None => {}
+        }
+    }
+    num_digits(regular_total).max(min_width)
+}
+
+/// Counts `filename`. The bytes of regular files are taken from their size when nothing
+/// else is counted, and, if `split` is set, large regular files are counted in parallel
+/// parts when more than one job is allowed.
+fn count_file(filename: &str, config: &Config, split: bool) -> MyResult<FileInfo> {
+    let bytes_only = config.bytes
+        && !(config.lines
+            || config.words
+            || config.chars
+            || config.graphemes
+            || config.max_line_length);
+
+    match regular_file_size(filename) {
+        Some(num_bytes) if bytes_only => Ok(FileInfo {
+            num_bytes,
+            ..Default::default()
+        }),
+        // The counts of decoded text can't be stitched together from parts cut anywhere.
+        Some(size)
+            if split
+                && config.jobs > 1
+                && !counts_text(config)
+                && filename != "-"
+                && size as u64 >= PARALLEL_SIZE_THRESHOLD =>
+        {
+            count_parallel(filename, size as u64, config)
+        }
+        _ => open(filename).and_then(|f| count(f, config)),
     }
 }
 
-#[derive(Debug, PartialEq)]
+/// Splits the regular file `filename` of `size` bytes into `config.jobs` byte ranges and
+/// counts them in parallel.
+fn count_parallel(filename: &str, size: u64, config: &Config) -> MyResult<FileInfo> {
+    let part_size = size.div_ceil(config.jobs as u64);
+    thread::scope(|s| {
+        let parts: Vec<_> = (0..size)
+            .step_by(part_size as usize)
+            .map(|start| {
+                let end = size.min(start + part_size);
+                s.spawn(move || {
+                    count_range(filename, start, end, config).map_err(|e| e.to_string())
+                })
+            })
+            .collect();
+
+        let mut info = FileInfo::default();
+        for part in parts {
+            info.add(&part.join().unwrap()?);
+        }
+        Ok(info)
+    })
+}
+
+/// Counts the bytes of `filename` from `start` up to `end`. The byte before `start` is read
+/// as well, so that a word crossing `start` is only counted by the range where it begins.
+fn count_range(filename: &str, start: u64, end: u64, config: &Config) -> MyResult<FileInfo> {
+    let mut file = File::open(filename)?;
+    let mut in_word = false;
+    if start > 0 {
+        let mut prev = [0];
+        file.seek(SeekFrom::Start(start - 1))?;
+        file.read_exact(&mut prev)?;
+        in_word = !is_space(prev[0]);
+    }
+    count_from(BufReader::new(file.take(end - start)), config, in_word)
+}
+
+#[derive(Debug, Default, PartialEq)]
 struct FileInfo {
     num_lines: usize,
     num_words: usize,
     num_bytes: usize,
     num_chars: usize,
+    num_graphemes: usize,
+    max_line_length: usize,
+}
+
+impl FileInfo {
+    /// Adds the counts of `other`, e.g. to compute the totals.
+    fn add(&mut self, other: &FileInfo) {
+        self.num_lines += other.num_lines;
+        self.num_words += other.num_words;
+        self.num_bytes += other.num_bytes;
+        self.num_chars += other.num_chars;
+        self.num_graphemes += other.num_graphemes;
+        self.max_line_length = self.max_line_length.max(other.max_line_length);
+    }
+}
+
+/// Counts the input in chunks of raw bytes, so that invalid UTF-8 doesn't stop the count.
+/// Lines are counted with memchr, words as transitions from whitespace to non-whitespace
+/// bytes and characters as UTF-8 leading bytes. Only the counts enabled in `config` are
+/// computed, apart from lines and bytes which come almost for free.
+fn count(file: impl BufRead, config: &Config) -> MyResult<FileInfo> {
+    count_from(file, config, false)
 }
 
-fn count(mut file: impl BufRead) -> MyResult<FileInfo> {
-    let mut num_lines = 0;
-    let mut num_words = 0;
-    let mut num_bytes = 0;
-    let mut num_chars = 0;
+/// Like [`count`], for input that may start in the middle of a word, as told by `in_word`.
+fn count_from(mut file: impl BufRead, config: &Config, mut in_word: bool) -> MyResult<FileInfo> {
+    let mut info = FileInfo::default();
+    let mut text = counts_text(config).then(TextCounter::default);
 
-    let mut buf = String::new();
     loop {
-        match file.read_line(&mut buf) {
-            Ok(0) => break,
-            Ok(b) => {
-                num_lines += 1;
-                num_bytes += b;
-                num_words += buf.split_whitespace().count();
-                num_chars += buf.chars().count();
-                buf.clear();
+        let buf = match file.fill_buf() {
+            Ok([]) => break,
+            Ok(buf) => buf,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(From::from(e)),
+        };
+
+        info.num_bytes += buf.len();
+        info.num_lines += memchr::memchr_iter(b'\n', buf).count();
+        if config.words && config.word_mode == WordMode::Whitespace {
+            info.num_words += count_words(buf, &mut in_word);
+        }
+        if config.chars {
+            // Every byte except UTF-8 continuation bytes (0b10xxxxxx) starts a character.
+            info.num_chars += buf.iter().filter(|&&b| (b as i8) >= -0x40).count();
+        }
+        if let Some(text) = &mut text {
+            text.add(buf, config, &mut info);
+        }
+
+        let len = buf.len();
+        file.consume(len);
+    }
+
+    if let Some(text) = text {
+        text.finish(config, &mut info);
+    }
+
+    Ok(info)
+}
+
+/// Whether any of the counts that need decoded text is requested.
+fn counts_text(config: &Config) -> bool {
+    config.max_line_length
+        || config.graphemes
+        || (config.words && config.word_mode == WordMode::Unicode)
+}
+
+/// Makes the counts that need decoded text, one chunk of the input at a time. Only what the
+/// next chunk may still change is carried over: an incomplete UTF-8 sequence, the last
+/// grapheme cluster and the last two word boundary segments, as a boundary between words
+/// can depend on the two characters after it, like in "can't" or "3.14".
+#[derive(Default)]
+struct TextCounter {
+    /// The start of a UTF-8 sequence cut off by the end of the previous chunk.
+    partial: Vec<u8>,
+    graphemes: String,
+    words: String,
+    width: LineWidth,
+}
+
+impl TextCounter {
+    fn add(&mut self, buf: &[u8], config: &Config, info: &mut FileInfo) {
+        let text = self.decode(buf);
+        self.count(&text, config, info, false);
+    }
+
+    /// Counts what was carried over at the end of the input.
+    fn finish(mut self, config: &Config, info: &mut FileInfo) {
+        let text = String::from_utf8_lossy(&std::mem::take(&mut self.partial)).into_owned();
+        self.count(&text, config, info, true);
+    }
+
+    /// Decodes `buf`, after the bytes left from the previous chunk, replacing invalid UTF-8
+    /// like [`String::from_utf8_lossy`]. A sequence cut off at the end is kept for later.
+    fn decode(&mut self, buf: &[u8]) -> String {
+        let joined;
+        let mut rest = if self.partial.is_empty() {
+            buf
+        } else {
+            self.partial.extend_from_slice(buf);
+            joined = std::mem::take(&mut self.partial);
+            &joined[..]
+        };
+
+        let mut text = String::with_capacity(rest.len());
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(valid) => {
+                    text.push_str(valid);
+                    break;
+                }
+                Err(e) => {
+                    let (valid, after) = rest.split_at(e.valid_up_to());
+                    text.push_str(std::str::from_utf8(valid).unwrap());
+                    match e.error_len() {
+                        Some(len) => {
+                            text.push(char::REPLACEMENT_CHARACTER);
+                            rest = &after[len..];
+                        }
+                        None => {
+                            self.partial = after.to_vec();
+                            break;
+                        }
+                    }
+                }
             }
-            Err(e) => {
-                num_lines += 1;
-                num_bytes += buf.len();
-                num_words += buf.split_whitespace().count();
-                num_chars += buf.chars().count();
-                eprintln!("{e}");
-                break;
+        }
+        text
+    }
+
+    /// Counts `text`, holding back the segments that may continue in the next chunk unless
+    /// this is the `last` one.
+    fn count(&mut self, text: &str, config: &Config, info: &mut FileInfo, last: bool) {
+        if config.max_line_length {
+            text.chars().for_each(|c| self.width.push(c));
+            info.max_line_length = info.max_line_length.max(self.width.max());
+        }
+        if config.graphemes {
+            self.graphemes.push_str(text);
+            let starts: Vec<_> = self
+                .graphemes
+                .grapheme_indices(true)
+                .map(|(i, _)| i)
+                .collect();
+            let done = if last {
+                starts.len()
+            } else {
+                starts.len().saturating_sub(1)
+            };
+            info.num_graphemes += done;
+            self.graphemes
+                .drain(..starts.get(done).copied().unwrap_or(self.graphemes.len()));
+        }
+        if config.words && config.word_mode == WordMode::Unicode {
+            self.words.push_str(text);
+            let segments: Vec<_> = self.words.split_word_bound_indices().collect();
+            let done = if last {
+                segments.len()
+            } else {
+                segments.len().saturating_sub(2)
+            };
+            info.num_words += segments[..done]
+                .iter()
+                .filter(|(_, segment)| segment.unicode_words().next().is_some())
+                .count();
+            let end = segments.get(done).map_or(self.words.len(), |(i, _)| *i);
+            self.words.drain(..end);
+        }
+    }
+}
+
+/// The display width of the widest line seen so far, with tabs expanded to the next multiple
+/// of 8 columns. Carriage returns and form feeds start a new segment, like newlines.
+#[derive(Default)]
+struct LineWidth {
+    width: usize,
+    max_width: usize,
+}
+
+impl LineWidth {
+    fn push(&mut self, c: char) {
+        match c {
+            '\n' | '\r' | '\x0c' => {
+                self.max_width = self.max_width.max(self.width);
+                self.width = 0;
             }
+            '\t' => self.width += 8 - self.width % 8,
+            _ => self.width += c.width().unwrap_or(0),
         }
     }
 
-    Ok(FileInfo {
-        num_lines,
-        num_words,
-        num_bytes,
-        num_chars,
-    })
+    fn max(&self) -> usize {
+        self.max_width.max(self.width)
+    }
+}
+
+/// Whitespace as defined by C's isspace in the C locale.
+fn is_space(b: u8) -> bool {
+    matches!(b, b' ' | b'\t'..=b'\r')
+}
+
+/// Counts the words starting in `buf`. `in_word` tells whether the byte before `buf` was
+/// part of a word, and is updated for the next chunk, so words spanning chunks are counted
+/// once. Blocks of 64 bytes are turned into bitmasks, which the compiler can vectorize.
+fn count_words(buf: &[u8], in_word: &mut bool) -> usize {
+    let mut words = 0;
+    let mut prev = *in_word as u64;
+
+    let mut blocks = buf.chunks_exact(64);
+    for block in &mut blocks {
+        let mut mask = 0u64;
+        for (i, &b) in block.iter().enumerate() {
+            mask |= (!is_space(b) as u64) << i;
+        }
+        // A word starts at every non-whitespace byte that follows a whitespace byte.
+        words += (mask & !((mask << 1) | prev)).count_ones() as usize;
+        prev = mask >> 63;
+    }
+    for &b in blocks.remainder() {
+        let word = !is_space(b) as u64;
+        words += (word & !prev) as usize;
+        prev = word;
+    }
+
+    *in_word = prev == 1;
+    words
+}
+
+/// Returns the size of `filename`, or of standard input for "-", when it is a regular file,
+/// so that the bytes can be counted without reading it.
+fn regular_file_size(filename: &str) -> Option<usize> {
+    if filename != "-" {
+        let metadata = fs::metadata(filename).ok()?;
+        return metadata.is_file().then_some(metadata.len() as usize);
+    }
+
+    #[cfg(unix)]
+    {
+        use std::io::Seek;
+        use std::os::fd::AsFd;
+
+        // Standard input may already have been partially read, e.g. by a parent shell script.
+        let mut stdin = fs::File::from(io::stdin().as_fd().try_clone_to_owned().ok()?);
+        let metadata = stdin.metadata().ok()?;
+        let position = stdin.stream_position().ok()?;
+        metadata
+            .is_file()
+            .then(|| metadata.len().saturating_sub(position) as usize)
+    }
+    #[cfg(not(unix))]
+    None
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{count, format_field, FileInfo};
-    use std::io::Cursor;
+    use super::{
+        count, count_parallel, count_words, Config, FileInfo, LineWidth, OutputFormat, TextCounter,
+        TotalMode, WordMode,
+    };
+    use std::fs::File;
+    use std::io::{BufReader, Cursor};
+
+    fn count_all() -> Config {
+        Config {
+            files: vec![],
+            lines: true,
+            words: true,
+            word_mode: WordMode::Whitespace,
+            bytes: true,
+            chars: true,
+            graphemes: true,
+            max_line_length: true,
+            total: TotalMode::Auto,
+            jobs: 1,
+            format: OutputFormat::Table,
+            by_extension: false,
+        }
+    }
 
     #[test]
     fn test_count() {
         let text = "I don't want the world. I just want your half.\r\n";
-        let info = count(Cursor::new(text));
+        let info = count(Cursor::new(text), &count_all());
         assert!(info.is_ok());
         let expected = FileInfo {
             num_lines: 1,
             num_words: 10,
             num_bytes: 48,
             num_chars: 48,
+            num_graphemes: 47,
+            max_line_length: 46,
         };
         assert_eq!(info.unwrap(), expected);
     }
 
     #[test]
-    fn test_format_field() {
-        assert_eq!(format_field(1, false), "");
-        assert_eq!(format_field(3, true), "       3");
-        assert_eq!(format_field(10, true), "      10");
+    fn test_count_bytes() {
+        // Invalid UTF-8 is counted rather than rejected, and a missing final newline
+        // doesn't make a line.
+        let text: &[u8] = b"caf\xc3\xa9 \xff\xfe\nno newline";
+        let info = count(Cursor::new(text), &count_all());
+        assert!(info.is_ok());
+        let expected = FileInfo {
+            num_lines: 1,
+            num_words: 4,
+            num_bytes: 19,
+            num_chars: 18,
+            num_graphemes: 18,
+            max_line_length: 10,
+        };
+        assert_eq!(info.unwrap(), expected);
+
+        // Words and lines spanning buffer boundaries are counted once.
+        let text = "lorem ipsum\tdolor\n sit amet ".repeat(100);
+        let info = count(BufReader::with_capacity(7, text.as_bytes()), &count_all());
+        assert!(info.is_ok());
+        let expected = FileInfo {
+            num_lines: 100,
+            num_words: 500,
+            num_bytes: 2800,
+            num_chars: 2800,
+            num_graphemes: 2800,
+            max_line_length: 29,
+        };
+        assert_eq!(info.unwrap(), expected);
+    }
+
+    #[test]
+    fn test_count_unicode() {
+        let mut config = count_all();
+        config.word_mode = WordMode::Unicode;
+
+        // Without a dictionary, UAX #29 makes a word of each ideograph, kana and Thai
+        // syllable. Punctuation isn't a word, and combining marks and CRLF belong to the
+        // preceding grapheme cluster.
+        let text = "東京へ行きます。\r\nสวัสดีครับ\nnaïve cafe\u{301}, \"hi\"\n";
+        let info = count(Cursor::new(text), &config);
+        assert!(info.is_ok());
+        let info = info.unwrap();
+        assert_eq!(info.num_lines, 3);
+        assert_eq!(info.num_chars, 39);
+        assert_eq!(info.num_graphemes, 34);
+        assert_eq!(info.num_words, 17);
+
+        // Whitespace separation sees one word in each of the first two lines.
+        let info = count(Cursor::new(text), &count_all()).unwrap();
+        assert_eq!(info.num_words, 5);
+
+        // Characters, grapheme clusters and words split across buffers are counted once.
+        let text = format!("{text}can't 3.14 e\u{301}\u{302} 👍🏽 🇫🇷🇩🇪 x\u{200d}y");
+        let expected = count(Cursor::new(&text), &config).unwrap();
+        for capacity in 1..=9 {
+            let info = count(BufReader::with_capacity(capacity, text.as_bytes()), &config);
+            assert_eq!(info.unwrap(), expected, "capacity {capacity}");
+        }
+    }
+
+    #[test]
+    fn test_text_counter_memory() {
+        let mut config = count_all();
+        config.word_mode = WordMode::Unicode;
+
+        // Without newlines, only the segments that may continue are held between chunks.
+        let mut counter = TextCounter::default();
+        let mut info = FileInfo::default();
+        for _ in 0..1000 {
+            counter.add(b"lorem ipsum caf\xc3", &config, &mut info);
+            counter.add(b"\xa9 ", &config, &mut info);
+            assert!(counter.graphemes.len() <= 4);
+            assert!(counter.words.len() <= 8);
+            assert!(counter.partial.len() <= 3);
+        }
+        counter.finish(&config, &mut info);
+        assert_eq!(info.num_words, 3000);
+        assert_eq!(info.num_graphemes, 17000);
+        assert_eq!(info.max_line_length, 17000);
+    }
+
+    #[test]
+    fn test_count_parallel() {
+        let filename = "tests/inputs/atlamal.txt";
+        let mut config = count_all();
+        config.max_line_length = false;
+        config.graphemes = false;
+        let expected = count(BufReader::new(File::open(filename).unwrap()), &config).unwrap();
+
+        // Parts of many sizes, down to single bytes, splitting words and multi-byte characters
+        for jobs in [1, 2, 3, 5, 7, 16, 64, 173, 200] {
+            config.jobs = jobs;
+            let info = count_parallel(filename, expected.num_bytes as u64, &config);
+            assert!(info.is_ok());
+            assert_eq!(info.unwrap(), expected, "{jobs} jobs");
+        }
+    }
+
+    #[test]
+    fn test_count_words() {
+        let mut in_word = false;
+        assert_eq!(count_words(b"", &mut in_word), 0);
+        assert_eq!(count_words(b"  one two\x0bthree ", &mut in_word), 3);
+        assert!(!in_word);
+        assert_eq!(count_words(b"fo", &mut in_word), 1);
+        assert!(in_word);
+        // The rest of the word from the previous chunk isn't a new word
+        assert_eq!(count_words(b"ur five", &mut in_word), 1);
+
+        let text = "word ".repeat(100);
+        let mut in_word = false;
+        assert_eq!(count_words(text.as_bytes(), &mut in_word), 100);
+        let text = "x".repeat(200);
+        let mut in_word = false;
+        assert_eq!(count_words(text.as_bytes(), &mut in_word), 1);
+    }
+
+    fn line_width(line: &str) -> usize {
+        let mut width = LineWidth::default();
+        line.chars().for_each(|c| width.push(c));
+        width.max()
+    }
+
+    #[test]
+    fn test_line_width() {
+        assert_eq!(line_width(""), 0);
+        assert_eq!(line_width("hello\n"), 5);
+        // Tabs advance to the next multiple of 8
+        assert_eq!(line_width("\tab\n"), 10);
+        assert_eq!(line_width("abc\tx"), 9);
+        // Wide characters take two columns
+        assert_eq!(line_width("日本語\n"), 6);
+        // Carriage returns start over
+        assert_eq!(line_width("long line\rshort\r\n"), 9);
     }
 }
@@ -0,0 +1,218 @@
+use crate::{Config, FileInfo, TotalMode};
+use command_utils::MyResult;
+use serde::Serialize;
+use std::io;
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum OutputFormat {
+    /// Right-aligned columns, like GNU wc.
+    Table,
+    /// One JSON object per line.
+    Json,
+    Csv,
+    Tsv,
+}
+
+/// The counts requested on the command line for one file, or for all of them when there is
+/// no path. Summaries by extension have the number of files and the extension instead of the
+/// path.
+#[derive(Serialize)]
+struct Record<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    files: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lines: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    words: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bytes: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chars: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    graphemes: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_line_length: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extension: Option<Option<&'a str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<Option<&'a str>>,
+}
+
+impl<'a> Record<'a> {
+    fn new(info: &FileInfo, config: &Config, path: Option<&'a str>) -> Record<'a> {
+        Record {
+            files: None,
+            lines: config.lines.then_some(info.num_lines),
+            words: config.words.then_some(info.num_words),
+            bytes: config.bytes.then_some(info.num_bytes),
+            chars: config.chars.then_some(info.num_chars),
+            graphemes: config.graphemes.then_some(info.num_graphemes),
+            max_line_length: config.max_line_length.then_some(info.max_line_length),
+            extension: None,
+            path: Some(path),
+        }
+    }
+
+    fn with_extension(
+        info: &FileInfo,
+        config: &Config,
+        files: usize,
+        extension: Option<&'a str>,
+    ) -> Record<'a> {
+        Record {
+            files: Some(files),
+            extension: Some(extension),
+            path: None,
+            ..Record::new(info, config, None)
+        }
+    }
+
+    /// The counts in the order they are printed.
+    fn values(&self) -> impl Iterator<Item = usize> {
+        [
+            self.files,
+            self.lines,
+            self.words,
+            self.bytes,
+            self.chars,
+            self.graphemes,
+            self.max_line_length,
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
+/// Prints the counts of each file and the totals in the format chosen on the command line.
+pub struct Printer<'a> {
+    config: &'a Config,
+    /// The width of the columns of the table format.
+    width: usize,
+    csv: Option<csv::Writer<io::Stdout>>,
+}
+
+impl<'a> Printer<'a> {
+    pub fn new(config: &'a Config, width: usize) -> Printer<'a> {
+        let delimiter = match config.format {
+            OutputFormat::Csv => Some(b','),
+            OutputFormat::Tsv => Some(b'\t'),
+            _ => None,
+        };
+        Printer {
+            config,
+            width,
+            csv: delimiter.map(|d| {
+                csv::WriterBuilder::new()
+                    .delimiter(d)
+                    .from_writer(io::stdout())
+            }),
+        }
+    }
+
+    pub fn print_file(&mut self, info: &FileInfo, filename: &str) -> MyResult<()> {
+        let record = Record::new(info, self.config, Some(filename));
+        match self.config.format {
+            // Like GNU wc, the name of standard input isn't printed.
+            OutputFormat::Table if filename == "-" => println!("{}", self.format_table(&record)),
+            OutputFormat::Table => println!("{} {filename}", self.format_table(&record)),
+            _ => self.print_record(&record)?,
+        }
+        Ok(())
+    }
+
+    /// Prints the counts of the `files` files with the `extension`, which is empty for the
+    /// files without one.
+    pub fn print_extension(
+        &mut self,
+        info: &FileInfo,
+        files: usize,
+        extension: &str,
+    ) -> MyResult<()> {
+        let record = Record::with_extension(info, self.config, files, Some(extension));
+        match self.config.format {
+            OutputFormat::Table if extension.is_empty() => {
+                println!("{} (none)", self.format_table(&record))
+            }
+            OutputFormat::Table => println!("{} .{extension}", self.format_table(&record)),
+            _ => self.print_record(&record)?,
+        }
+        Ok(())
+    }
+
+    /// Prints the totals, with the number of files when they are summarized by extension.
+    pub fn print_total(&mut self, info: &FileInfo, files: Option<usize>) -> MyResult<()> {
+        let record = match files {
+            Some(files) => Record::with_extension(info, self.config, files, None),
+            None => Record::new(info, self.config, None),
+        };
+        match self.config.format {
+            OutputFormat::Table if self.config.total == TotalMode::Only => {
+                println!("{}", self.format_table(&record))
+            }
+            OutputFormat::Table => println!("{} total", self.format_table(&record)),
+            _ => self.print_record(&record)?,
+        }
+        Ok(())
+    }
+
+    /// Flushes the records that are still buffered.
+    pub fn finish(self) -> MyResult<()> {
+        if let Some(mut csv) = self.csv {
+            csv.flush()?;
+        }
+        Ok(())
+    }
+
+    fn format_table(&self, record: &Record) -> String {
+        record
+            .values()
+            .map(|v| format_field(v, self.width))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn print_record(&mut self, record: &Record) -> MyResult<()> {
+        match &mut self.csv {
+            Some(csv) => csv.serialize(record)?,
+            None => println!("{}", serde_json::to_string(record)?),
+        }
+        Ok(())
+    }
+}
+
+fn format_field(value: usize, width: usize) -> String {
+    format!("{value:>width$}")
+}
+
+/// Returns the number of digits of `value`.
+pub fn num_digits(mut value: u64) -> usize {
+    let mut digits = 1;
+    while value >= 10 {
+        value /= 10;
+        digits += 1;
+    }
+    digits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_field, num_digits};
+
+    #[test]
+    fn test_format_field() {
+        assert_eq!(format_field(1, 1), "1");
+        assert_eq!(format_field(3, 8), "       3");
+        assert_eq!(format_field(10, 8), "      10");
+        // Values wider than the column aren't truncated
+        assert_eq!(format_field(12345, 3), "12345");
+    }
+
+    #[test]
+    fn test_num_digits() {
+        assert_eq!(num_digits(0), 1);
+        assert_eq!(num_digits(9), 1);
+        assert_eq!(num_digits(10), 2);
+        assert_eq!(num_digits(225), 3);
+        assert_eq!(num_digits(u64::MAX), 20);
+    }
+}
@@ -0,0 +1,184 @@
+use command_utils::{find_files_pruned, MyResult};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::gitignore::Gitignore;
+use ignore::Match;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Replaces the directories in `paths` with the files found under them, leaving out the
+/// files ignored by a `.gitignore` in the directory walked or any directory below it, and
+/// the ones with a path or path component matching one of the `excludes` globs. Other paths,
+/// including "-", are kept as they are.
+pub fn expand_dirs(paths: &[String], excludes: &[String]) -> MyResult<Vec<String>> {
+    let mut builder = GlobSetBuilder::new();
+    for exclude in excludes {
+        builder.add(Glob::new(exclude).map_err(|e| format!("--exclude: {e}"))?);
+    }
+    let excludes = builder.build()?;
+
+    let mut files = Vec::new();
+    for path in paths {
+        if !Path::new(path).is_dir() {
+            files.push(path.to_owned());
+            continue;
+        }
+        let filter = WalkFilter::new(Path::new(path), &excludes);
+        // The order of directory entries depends on the file system, so sort them to print
+        // the same output everywhere.
+        let mut found: Vec<_> = find_files_pruned(
+            std::slice::from_ref(path),
+            true,
+            |dir| filter.is_included(dir, true),
+            |p| filter.is_included(p, false),
+        )?
+        .collect();
+        found.sort();
+        files.extend(found.iter().map(|p| p.to_string_lossy().into_owned()));
+    }
+    Ok(files)
+}
+
+/// Decides which of the directories below `root` are walked and which of the files found
+/// are counted. The `.gitignore` files are read as the walk reaches their directories.
+struct WalkFilter<'a> {
+    root: &'a Path,
+    excludes: &'a GlobSet,
+    gitignores: RefCell<HashMap<PathBuf, Option<Gitignore>>>,
+}
+
+impl<'a> WalkFilter<'a> {
+    fn new(root: &'a Path, excludes: &'a GlobSet) -> WalkFilter<'a> {
+        WalkFilter {
+            root,
+            excludes,
+            gitignores: RefCell::new(HashMap::new()),
+        }
+    }
+
+    fn is_included(&self, path: &Path, is_dir: bool) -> bool {
+        let Ok(relative) = path.strip_prefix(self.root) else {
+            return true;
+        };
+        let excluded = relative
+            .ancestors()
+            .filter(|p| !p.as_os_str().is_empty())
+            .any(|p| {
+                p.file_name() == Some(".git".as_ref())
+                    || self.excludes.is_match(p)
+                    || p.file_name()
+                        .is_some_and(|name| self.excludes.is_match(name))
+            });
+        if excluded {
+            return false;
+        }
+
+        // The .gitignore closest to the file takes precedence, like in git.
+        let mut gitignores = self.gitignores.borrow_mut();
+        for dir in path.ancestors().skip(1) {
+            let gitignore = gitignores
+                .entry(dir.to_path_buf())
+                .or_insert_with(|| read_gitignore(dir));
+            if let Some(gitignore) = gitignore {
+                match gitignore.matched_path_or_any_parents(path, is_dir) {
+                    Match::Ignore(_) => return false,
+                    Match::Whitelist(_) => return true,
+                    Match::None => {}
+                }
+            }
+            if dir == self.root {
+                break;
+            }
+        }
+        true
+    }
+}
+
+/// Reads the `.gitignore` in `dir`, if there is one. Invalid patterns are reported and
+/// skipped.
+fn read_gitignore(dir: &Path) -> Option<Gitignore> {
+    let path = dir.join(".gitignore");
+    if !path.is_file() {
+        return None;
+    }
+    let (gitignore, err) = Gitignore::new(&path);
+    if let Some(e) = err {
+        eprintln!("{}: {e}", path.display());
+    }
+    Some(gitignore)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expand_dirs, WalkFilter};
+    use command_utils::find_files_pruned;
+    use globset::{Glob, GlobSetBuilder};
+    use std::cell::RefCell;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn test_expand_dirs() {
+        let tree = "tests/inputs/tree".to_string();
+        let files = expand_dirs(&[tree.clone(), "-".to_string()], &[]);
+        assert!(files.is_ok());
+        let files = files.unwrap();
+        // target/ and *.log are ignored by tree/.gitignore, and vendor/.gitignore ignores
+        // skip.md but not keep.md.
+        assert!(!files
+            .iter()
+            .any(|f| f.contains("target") || f.ends_with(".log")));
+        assert!(!files.iter().any(|f| f.ends_with("skip.md")));
+        assert!(files.iter().any(|f| f.ends_with("vendor/keep.md")));
+        assert_eq!(files.last().unwrap(), "-");
+
+        let files = expand_dirs(
+            std::slice::from_ref(&tree),
+            &["nested".to_string(), "*.md".to_string()],
+        );
+        assert!(files.is_ok());
+        let files = files.unwrap();
+        assert!(!files
+            .iter()
+            .any(|f| f.contains("nested") || f.ends_with(".md")));
+        assert!(files.iter().any(|f| f.ends_with("src/main.rs")));
+
+        assert!(expand_dirs(&[tree], &["[a-".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_excluded_dirs_not_walked() {
+        let tree = "tests/inputs/tree".to_string();
+        let excludes = GlobSetBuilder::new()
+            .add(Glob::new("nested").unwrap())
+            .build()
+            .unwrap();
+        let filter = WalkFilter::new(Path::new(&tree), &excludes);
+        let seen = RefCell::new(Vec::<PathBuf>::new());
+        let files = find_files_pruned(
+            std::slice::from_ref(&tree),
+            true,
+            |dir| {
+                seen.borrow_mut().push(dir.to_path_buf());
+                filter.is_included(dir, true)
+            },
+            |p| {
+                seen.borrow_mut().push(p.to_path_buf());
+                filter.is_included(p, false)
+            },
+        );
+        assert!(files.is_ok());
+        let files: Vec<_> = files.unwrap().collect();
+        assert!(files.iter().any(|p| p.ends_with("src/main.rs")));
+
+        // target/ is ignored by tree/.gitignore and nested/ is excluded, so nothing below
+        // them is ever read.
+        let seen = seen.into_inner();
+        assert!(seen.iter().any(|p| p.ends_with("target")));
+        assert!(seen.iter().any(|p| p.ends_with("nested")));
+        let below = |p: &PathBuf, dir: &str| p.parent().is_some_and(|p| p.starts_with(dir));
+        assert!(!seen
+            .iter()
+            .any(|p| below(p, "tests/inputs/tree/target")
+                || below(p, "tests/inputs/tree/src/nested")));
+    }
+}
@@ -8,6 +8,9 @@ const PRG: &str = "wcr";
 const EMPTY: &str = "tests/inputs/empty.txt";
 const FOX: &str = "tests/inputs/fox.txt";
 const ATLAMAL: &str = "tests/inputs/atlamal.txt";
+const FILES0: &str = "tests/inputs/files0.txt";
+const UNICODE: &str = "tests/inputs/unicode.txt";
+const TREE: &str = "tests/inputs/tree";
 
 #[test]
 fn dies_chars_and_bytes() -> TestResult {
@@ -22,6 +25,42 @@ fn dies_chars_and_bytes() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn dies_files0_from_and_files() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--files0-from", FILES0, FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "the argument '--files0-from <F>' cannot be used with '[FILE]...'",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn dies_zero_length_file_name() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--files0-from", "-"])
+        .write_stdin("tests/inputs/fox.txt\0\0")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("-: invalid zero-length file name"));
+
+    Ok(())
+}
+
+#[test]
+fn dies_invalid_exclude_glob() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-r", "--exclude", "[a-", TREE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--exclude: "));
+
+    Ok(())
+}
+
 #[test]
 fn skips_bad_file() -> TestResult {
     let bad = &gen_bad_file();
@@ -58,6 +97,23 @@ fn skips_bad_file() -> TestResult {
 #[case(&["-w", "-c", EMPTY, FOX, ATLAMAL], "tests/expected/all.txt.wc.out")]
 #[case(&["-w", "-l", EMPTY, FOX, ATLAMAL], "tests/expected/all.txt.wl.out")]
 #[case(&["-l", "-c", EMPTY, FOX, ATLAMAL], "tests/expected/all.txt.cl.out")]
+#[case(&["-L", FOX], "tests/expected/fox.txt.L.out")]
+#[case(&["--max-line-length", ATLAMAL], "tests/expected/atlamal.txt.L.out")]
+#[case(&["-l", "-L", EMPTY, FOX, ATLAMAL], "tests/expected/all.lL.out")]
+#[case(&["--total=only", EMPTY, FOX, ATLAMAL], "tests/expected/all.total-only.out")]
+#[case(&["--total=never", EMPTY, FOX, ATLAMAL], "tests/expected/all.total-never.out")]
+#[case(&["--total=always", FOX], "tests/expected/fox.txt.total-always.out")]
+#[case(&["--files0-from", FILES0], "tests/expected/files0.txt.out")]
+#[case(&["--format=json", EMPTY, FOX, ATLAMAL], "tests/expected/all.json.out")]
+#[case(&["--format=csv", "-l", "-m", EMPTY, FOX, ATLAMAL], "tests/expected/all.ml.csv.out")]
+#[case(&["--format=tsv", "-L", FOX], "tests/expected/fox.txt.L.tsv.out")]
+#[case(&["--format=json", "--total=only", EMPTY, FOX], "tests/expected/all.total-only.json.out")]
+#[case(&["-w", UNICODE], "tests/expected/unicode.txt.w.out")]
+#[case(&["--words=unicode", "--graphemes", UNICODE], "tests/expected/unicode.txt.w-unicode.graphemes.out")]
+#[case(&["--format=json", "--graphemes", "-m", UNICODE], "tests/expected/unicode.txt.graphemes.json.out")]
+#[case(&["-r", TREE], "tests/expected/tree.r.out")]
+#[case(&["--recursive", "--by-extension", TREE], "tests/expected/tree.by-extension.out")]
+#[case(&["-r", "--exclude", "build", "--exclude", "src/*", "-l", "--by-extension", "--format=json", TREE], "tests/expected/tree.exclude.json.out")]
 fn run(#[case] args: &[&str], #[case] expected_file: &str) -> TestResult {
     let expected = fs::read_to_string(expected_file)?;
 
@@ -70,6 +126,65 @@ fn run(#[case] args: &[&str], #[case] expected_file: &str) -> TestResult {
     Ok(())
 }
 
+#[rstest]
+#[case(&[EMPTY, FOX, ATLAMAL])]
+#[case(&["-l", "-m", ATLAMAL, FOX, EMPTY, FOX])]
+fn jobs_keep_argument_order(#[case] args: &[&str]) -> TestResult {
+    let output = Command::cargo_bin(PRG)?.args(args).output()?;
+    let expected = String::from_utf8(output.stdout)?;
+
+    for jobs in ["2", "3", "0"] {
+        Command::cargo_bin(PRG)?
+            .args(["-j", jobs])
+            .args(args)
+            .assert()
+            .success()
+            .stdout(expected.clone());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn fox_stdin() -> TestResult {
+    // The size of a pipe is unknown, so the columns are 7 wide as in GNU wc
+    let input = fs::read_to_string(FOX)?;
+    Command::cargo_bin(PRG)?
+        .write_stdin(input)
+        .assert()
+        .stdout("      1       9      48\n");
+
+    Ok(())
+}
+
+#[test]
+fn jobs_read_stdin_once() -> TestResult {
+    // Only the first "-" gets the input, however many workers there are.
+    let input = fs::read_to_string(FOX)?;
+    let args = ["-", FOX, "-", "-"];
+    let output = Command::cargo_bin(PRG)?
+        .args(args)
+        .write_stdin(input.clone())
+        .output()?;
+    let expected = String::from_utf8(output.stdout)?;
+    let lines: Vec<_> = expected.lines().collect();
+    assert!(lines[0].starts_with("      1       9      48"));
+    assert!(lines[2].starts_with("      0       0       0"));
+    assert!(lines[3].starts_with("      0       0       0"));
+
+    for jobs in ["2", "4"] {
+        Command::cargo_bin(PRG)?
+            .args(["-j", jobs])
+            .args(args)
+            .write_stdin(input.clone())
+            .assert()
+            .success()
+            .stdout(expected.clone());
+    }
+
+    Ok(())
+}
+
 #[test]
 fn altamal_stdin() -> TestResult {
     let input = fs::read_to_string(ATLAMAL)?;
@@ -0,0 +1 @@
+build artifact